@@ -1,4 +1,5 @@
 use crate::db;
+use crate::error::ClipboardError;
 use crate::settings;
 use rusqlite::{params, OptionalExtension};
 use serde::{Deserialize, Serialize};
@@ -13,549 +14,5227 @@ pub struct ClipboardItem {
     pub content_type: String, // "text", "image", "file"
     pub created_at: u64,
     pub is_favorite: bool,
+    /// 归一化改变了内容时保存的原始文本（用于精确还原）
+    #[serde(default)]
+    pub raw_content: Option<String>,
+    /// 内容来源应用（可能为空）
+    #[serde(default)]
+    pub source_app: Option<String>,
+    /// 备注（如导入来源文件名）
+    #[serde(default)]
+    pub note: Option<String>,
+    /// 图片项的主色调缓存（"#rrggbb"）
+    #[serde(default)]
+    pub dominant_color: Option<String>,
+    /// 表格项检测到的行数
+    #[serde(default)]
+    pub table_rows: Option<u32>,
+    /// 表格项检测到的列数
+    #[serde(default)]
+    pub table_cols: Option<u32>,
+    /// 同一次多文件复制产生的项共享的分组 id
+    #[serde(default)]
+    pub group_id: Option<String>,
+    /// 单行标题，插入/编辑时由内容推导
+    #[serde(default)]
+    pub title: Option<String>,
+    /// 标签列表（数据库中存 JSON 数组）
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// 图片项的存储格式（如 "image/png"）
+    #[serde(default)]
+    pub mime_type: Option<String>,
+    /// 图片文件的磁盘大小（字节）
+    #[serde(default)]
+    pub image_bytes: Option<u64>,
+    /// 所属配置档（空表示默认档）
+    #[serde(default)]
+    pub profile: Option<String>,
+    /// 图片项的缩略图文件路径（原图足够小或生成失败时为空）
+    #[serde(default)]
+    pub thumbnail_path: Option<String>,
+    /// 文本超过入库字节上限被截断时为 true，界面据此提示内容不完整
+    #[serde(default)]
+    pub truncated: bool,
+    /// 文本子类型（"url"/"email"/"hex_color"/"code"/"plain"），仅文本项有值
+    #[serde(default)]
+    pub kind: Option<String>,
+    /// 捕获到的富文本变体（HTML 片段或 RTF 原文），纯文本复制为空
+    #[serde(default)]
+    pub rich_content: Option<String>,
+    /// 富文本变体的格式（"html" 或 "rtf"）
+    #[serde(default)]
+    pub rich_format: Option<String>,
+    /// 列表预览，读取时按用户设置即时计算，不落库
+    #[serde(default)]
+    pub preview: Option<String>,
 }
 
-fn now_ts() -> u64 {
-    SystemTime::now()
-        .duration_since(UNIX_EPOCH)
+/// clipboard_history 的查询列顺序，与 map_item_row 保持一致
+const ITEM_COLUMNS: &str =
+    "id, content, content_type, created_at, is_favorite, raw_content, source_app, note, dominant_color, table_rows, table_cols, group_id, title, tags, mime_type, image_bytes, profile, thumbnail_path, truncated, kind, rich_content, rich_format";
+
+fn map_item_row(row: &rusqlite::Row) -> rusqlite::Result<ClipboardItem> {
+    Ok(ClipboardItem {
+        id: row.get(0)?,
+        content: row.get(1)?,
+        content_type: row.get(2)?,
+        created_at: row.get::<_, i64>(3)? as u64,
+        is_favorite: row.get::<_, i64>(4)? != 0,
+        raw_content: row.get(5)?,
+        source_app: row.get(6)?,
+        note: row.get(7)?,
+        dominant_color: row.get(8)?,
+        table_rows: row.get::<_, Option<i64>>(9)?.map(|v| v as u32),
+        table_cols: row.get::<_, Option<i64>>(10)?.map(|v| v as u32),
+        group_id: row.get(11)?,
+        title: row.get(12)?,
+        tags: {
+            let tags_json: Option<String> = row.get(13)?;
+            tags_json
+                .and_then(|json| serde_json::from_str(&json).ok())
+                .unwrap_or_default()
+        },
+        mime_type: row.get(14)?,
+        image_bytes: row.get::<_, Option<i64>>(15)?.map(|v| v as u64),
+        profile: row.get(16)?,
+        thumbnail_path: row.get(17)?,
+        truncated: row.get::<_, i64>(18)? != 0,
+        kind: row.get(19)?,
+        rich_content: row.get(20)?,
+        rich_format: row.get(21)?,
+        preview: None,
+    })
+}
+
+/// 按用户设置生成单条预览：可选折叠换行，并按字符数截断
+fn compute_preview(content: &str, max_chars: u32, collapse_newlines: bool) -> String {
+    let text = if collapse_newlines {
+        content.split_whitespace().collect::<Vec<_>>().join(" ")
+    } else {
+        content.trim().to_string()
+    };
+
+    let max_chars = max_chars.max(1) as usize;
+    if text.chars().count() > max_chars {
+        let truncated: String = text.chars().take(max_chars).collect();
+        format!("{}…", truncated)
+    } else {
+        text
+    }
+}
+
+/// 为一批条目补上即时计算的预览，设置变更后下次列出即生效
+fn apply_previews(items: &mut [ClipboardItem], app_data_dir: &PathBuf) {
+    // 先做透明解密（或锁定占位），预览基于用户实际看到的内容
+    apply_decryption(items);
+    let settings = settings::load_settings(app_data_dir).unwrap_or_default();
+    for item in items.iter_mut() {
+        // 老数据没有子类型，读取时就地分类（不回写，下次读仍会算一遍）
+        if item.kind.is_none() && item.content_type == "text" && item.content != LOCKED_PLACEHOLDER
+        {
+            item.kind = Some(detect_text_kind(&item.content).to_string());
+        }
+        item.preview = Some(compute_preview(
+            &item.content,
+            settings.clipboard_preview_max_chars,
+            settings.clipboard_preview_collapse_newlines,
+        ));
+    }
+}
+
+/// 剪切板监控日志级别
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Info,
+    Warn,
+    Error,
+}
+
+/// 宿主可注册的日志钩子，接收级别和一行结构化 JSON
+pub type MonitorLogHook = Box<dyn Fn(LogLevel, &str) + Send + Sync>;
+
+static MONITOR_LOG_HOOK: std::sync::OnceLock<MonitorLogHook> = std::sync::OnceLock::new();
+
+/// 注册监控日志钩子（只能注册一次），未注册时默认写入日志文件
+pub fn set_monitor_log_hook(hook: MonitorLogHook) -> Result<(), String> {
+    MONITOR_LOG_HOOK
+        .set(hook)
+        .map_err(|_| "Monitor log hook already set".to_string())
+}
+
+/// 监控的持续性故障状态（如图片目录不可写），None 表示一切正常
+static MONITOR_STATUS: std::sync::Mutex<Option<String>> = std::sync::Mutex::new(None);
+
+/// 设置或清除监控故障状态
+pub fn set_monitor_status(status: Option<String>) {
+    if let Ok(mut guard) = MONITOR_STATUS.lock() {
+        *guard = status;
+    }
+}
+
+/// 读取监控故障状态，供 UI 解释"为什么截图没进历史"
+pub fn get_monitor_status() -> Option<String> {
+    MONITOR_STATUS.lock().ok().and_then(|guard| guard.clone())
+}
+
+/// 监控的内存去重状态：最近一次捕获的文本与图片哈希
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct MonitorDedupState {
+    pub last_text: String,
+    pub last_image: String,
+    pub last_file: String,
+}
+
+static MONITOR_DEDUP: std::sync::Mutex<MonitorDedupState> =
+    std::sync::Mutex::new(MonitorDedupState {
+        last_text: String::new(),
+        last_image: String::new(),
+        last_file: String::new(),
+    });
+
+/// 读取监控去重状态（调试用）
+pub fn get_monitor_dedup_state() -> MonitorDedupState {
+    MONITOR_DEDUP.lock().map(|g| g.clone()).unwrap_or_default()
+}
+
+/// 清空监控去重状态，让下一次相同内容的复制重新被捕获
+pub fn reset_monitor_dedup_state() {
+    if let Ok(mut guard) = MONITOR_DEDUP.lock() {
+        *guard = MonitorDedupState::default();
+    }
+}
+
+fn monitor_dedup_last_text() -> String {
+    MONITOR_DEDUP
+        .lock()
+        .map(|g| g.last_text.clone())
         .unwrap_or_default()
-        .as_secs()
 }
 
-/// 获取所有剪切板历史
-pub fn get_all_clipboard_items(app_data_dir: &PathBuf) -> Result<Vec<ClipboardItem>, String> {
-    let conn = db::get_readonly_connection(app_data_dir)?;
+fn set_monitor_dedup_last_text(content: &str) {
+    if let Ok(mut guard) = MONITOR_DEDUP.lock() {
+        guard.last_text = content.to_string();
+    }
+}
 
-    let mut stmt = conn
-        .prepare("SELECT id, content, content_type, created_at, is_favorite FROM clipboard_history ORDER BY created_at DESC")
-        .map_err(|e| format!("Failed to prepare clipboard query: {}", e))?;
+fn monitor_dedup_last_image() -> String {
+    MONITOR_DEDUP
+        .lock()
+        .map(|g| g.last_image.clone())
+        .unwrap_or_default()
+}
 
-    let rows = stmt
-        .query_map([], |row| {
-            Ok(ClipboardItem {
-                id: row.get(0)?,
-                content: row.get(1)?,
-                content_type: row.get(2)?,
-                created_at: row.get::<_, i64>(3)? as u64,
-                is_favorite: row.get::<_, i64>(4)? != 0,
-            })
-        })
-        .map_err(|e| format!("Failed to iterate clipboard items: {}", e))?;
+fn set_monitor_dedup_last_image(hash: &str) {
+    if let Ok(mut guard) = MONITOR_DEDUP.lock() {
+        guard.last_image = hash.to_string();
+    }
+}
 
-    let mut items = Vec::new();
-    for row in rows {
-        items.push(row.map_err(|e| format!("Failed to read clipboard row: {}", e))?);
+/// 监控暂停开关：置位后事件处理和轮询都跳过读取，线程保持存活
+static MONITOR_PAUSED: std::sync::atomic::AtomicBool =
+    std::sync::atomic::AtomicBool::new(false);
+
+/// 轮询型监控的间隔（毫秒），运行期可调、下一轮生效
+static MONITOR_POLL_INTERVAL_MS: std::sync::atomic::AtomicU64 =
+    std::sync::atomic::AtomicU64::new(500);
+
+/// 暂停剪切板监控（不退出监控线程，恢复后继续捕获）
+pub fn pause_clipboard_monitor() {
+    MONITOR_PAUSED.store(true, std::sync::atomic::Ordering::Relaxed);
+}
+
+/// 恢复剪切板监控
+pub fn resume_clipboard_monitor() {
+    MONITOR_PAUSED.store(false, std::sync::atomic::Ordering::Relaxed);
+}
+
+pub fn is_clipboard_monitor_paused() -> bool {
+    MONITOR_PAUSED.load(std::sync::atomic::Ordering::Relaxed)
+}
+
+/// 调整轮询间隔，过小的值按 50ms 兜底避免空转
+pub fn set_clipboard_poll_interval(ms: u64) {
+    MONITOR_POLL_INTERVAL_MS.store(ms.max(50), std::sync::atomic::Ordering::Relaxed);
+}
+
+pub fn get_clipboard_poll_interval() -> u64 {
+    MONITOR_POLL_INTERVAL_MS.load(std::sync::atomic::Ordering::Relaxed)
+}
+
+fn monitor_dedup_last_file() -> String {
+    MONITOR_DEDUP
+        .lock()
+        .map(|g| g.last_file.clone())
+        .unwrap_or_default()
+}
+
+fn set_monitor_dedup_last_file(paths: &str) {
+    if let Ok(mut guard) = MONITOR_DEDUP.lock() {
+        guard.last_file = paths.to_string();
     }
-    Ok(items)
 }
 
-/// 添加剪切板项
-pub fn add_clipboard_item(
-    content: String,
-    content_type: String,
-    app_data_dir: &PathBuf,
-) -> Result<ClipboardItem, String> {
-    let now = now_ts();
-    let id = format!("clipboard-{}", now);
+/// 加密内容在 content 列里的前缀标识，后跟 base64(nonce || 密文)
+const ENCRYPTED_PREFIX: &str = "enc:v1:";
+/// 锁定状态下读取返回的占位内容
+const LOCKED_PLACEHOLDER: &str = "••••••••";
+/// 校验口令用的固定明文：加密后存进配置，解锁时解回比对
+const ENCRYPTION_CHECK: &str = "refast-encryption-check";
+/// 加密配置在 settings 表里的键
+const ENCRYPTION_CONFIG_KEY: &str = "clipboard_encryption";
 
-    let item = ClipboardItem {
-        id: id.clone(),
-        content: content.clone(),
-        content_type: content_type.clone(),
-        created_at: now,
-        is_favorite: false,
-    };
+/// 解锁后的派生密钥，只存在于内存，锁定即清空
+static ENCRYPTION_KEY: std::sync::Mutex<Option<[u8; 32]>> = std::sync::Mutex::new(None);
 
-    let conn = db::get_connection(app_data_dir)?;
-    
-    // 检查是否已存在相同内容（避免重复）
-    let existing: Option<String> = conn
+/// settings 表里保存的加密配置：盐和派生参数，绝不保存口令本身
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct EncryptionConfig {
+    pub enabled: bool,
+    /// base64 编码的随机盐
+    pub salt: String,
+    pub m_cost: u32,
+    pub t_cost: u32,
+    pub p_cost: u32,
+    /// 用派生密钥加密 ENCRYPTION_CHECK 的结果，解锁时验证口令是否正确
+    pub verifier: String,
+}
+
+/// 加密功能的当前状态（给设置界面展示）
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ClipboardEncryptionStatus {
+    pub enabled: bool,
+    /// 已启用但密钥不在内存中（需要口令解锁）
+    pub locked: bool,
+}
+
+fn load_encryption_config(
+    conn: &rusqlite::Connection,
+) -> Result<Option<EncryptionConfig>, String> {
+    let value: Option<String> = conn
         .query_row(
-            "SELECT id FROM clipboard_history WHERE content = ?1 AND content_type = ?2",
-            params![content, content_type],
+            "SELECT value FROM settings WHERE key = ?1 LIMIT 1",
+            params![ENCRYPTION_CONFIG_KEY],
             |row| row.get(0),
         )
         .optional()
-        .map_err(|e| format!("Failed to check existing clipboard: {}", e))?;
-    
-    if let Some(existing_id) = existing {
-        // 如果已存在，更新时间戳
-        conn.execute(
-            "UPDATE clipboard_history SET created_at = ?1 WHERE id = ?2",
-            params![now as i64, existing_id],
-        )
-        .map_err(|e| format!("Failed to update clipboard timestamp: {}", e))?;
-        
-        return Ok(ClipboardItem {
-            id: existing_id,
-            content,
-            content_type,
-            created_at: now,
-            is_favorite: false,
-        });
+        .map_err(|e| format!("Failed to load encryption config: {}", e))?;
+
+    match value {
+        Some(json) => serde_json::from_str(&json)
+            .map(Some)
+            .map_err(|e| format!("Failed to parse encryption config: {}", e)),
+        None => Ok(None),
     }
+}
 
+fn save_encryption_config(
+    conn: &rusqlite::Connection,
+    config: &EncryptionConfig,
+) -> Result<(), String> {
+    let json = serde_json::to_string(config)
+        .map_err(|e| format!("Failed to serialize encryption config: {}", e))?;
     conn.execute(
-        "INSERT INTO clipboard_history (id, content, content_type, created_at, is_favorite)
-         VALUES (?1, ?2, ?3, ?4, ?5)",
-        params![item.id, item.content, item.content_type, item.created_at as i64, 0],
+        "INSERT INTO settings (key, value) VALUES (?1, ?2)
+         ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+        params![ENCRYPTION_CONFIG_KEY, json],
     )
-    .map_err(|e| format!("Failed to insert clipboard item: {}", e))?;
+    .map_err(|e| format!("Failed to save encryption config: {}", e))?;
+    Ok(())
+}
 
-    // 检查并限制最大数量
-    enforce_max_items(app_data_dir)?;
+fn clipboard_encryption_enabled(conn: &rusqlite::Connection) -> bool {
+    load_encryption_config(conn)
+        .ok()
+        .flatten()
+        .map(|c| c.enabled)
+        .unwrap_or(false)
+}
 
-    Ok(item)
+fn current_encryption_key() -> Option<[u8; 32]> {
+    ENCRYPTION_KEY.lock().ok().and_then(|g| *g)
 }
 
-/// 限制剪切板历史的最大数量，删除超出部分的记录
-fn enforce_max_items(app_data_dir: &PathBuf) -> Result<(), String> {
-    // 获取设置中的最大数量
-    let settings = settings::load_settings(app_data_dir)
-        .unwrap_or_default();
-    let max_items = settings.clipboard_max_items;
-    
-    if max_items == 0 {
-        // 0 表示不限制
-        return Ok(());
+fn is_encrypted_content(content: &str) -> bool {
+    content.starts_with(ENCRYPTED_PREFIX)
+}
+
+/// 加密覆盖的内容类型：文本以及由文本检测出的表格；
+/// 图片/文件条目的 content 是路径，不加密
+fn is_encryptable_type(content_type: &str) -> bool {
+    matches!(content_type, "text" | "table")
+}
+
+/// 按配置的 argon2id 参数从口令派生 32 字节密钥
+fn derive_encryption_key(
+    passphrase: &str,
+    salt: &[u8],
+    m_cost: u32,
+    t_cost: u32,
+    p_cost: u32,
+) -> Result<[u8; 32], String> {
+    let params = argon2::Params::new(m_cost, t_cost, p_cost, Some(32))
+        .map_err(|e| format!("Failed to build argon2 params: {}", e))?;
+    let argon = argon2::Argon2::new(argon2::Algorithm::Argon2id, argon2::Version::V0x13, params);
+
+    let mut key = [0u8; 32];
+    argon
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| format!("Failed to derive encryption key: {}", e))?;
+    Ok(key)
+}
+
+/// XChaCha20-Poly1305 加密：随机 nonce 与密文一起 base64 后带前缀存储
+fn encrypt_content(key: &[u8; 32], plaintext: &str) -> Result<String, String> {
+    use base64::Engine;
+    use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+    use chacha20poly1305::XChaCha20Poly1305;
+
+    let cipher = XChaCha20Poly1305::new(key.into());
+    let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let ciphertext = cipher
+        .encrypt(&nonce, plaintext.as_bytes())
+        .map_err(|e| format!("Failed to encrypt content: {}", e))?;
+
+    let mut payload = nonce.to_vec();
+    payload.extend_from_slice(&ciphertext);
+    Ok(format!(
+        "{}{}",
+        ENCRYPTED_PREFIX,
+        base64::engine::general_purpose::STANDARD.encode(&payload)
+    ))
+}
+
+fn decrypt_content(key: &[u8; 32], stored: &str) -> Result<String, String> {
+    use base64::Engine;
+    use chacha20poly1305::aead::{Aead, KeyInit};
+    use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+
+    let encoded = stored
+        .strip_prefix(ENCRYPTED_PREFIX)
+        .ok_or_else(|| "Content is not encrypted".to_string())?;
+    let payload = base64::engine::general_purpose::STANDARD
+        .decode(encoded)
+        .map_err(|e| format!("Failed to decode encrypted content: {}", e))?;
+    if payload.len() < 24 {
+        return Err("Encrypted content too short".to_string());
+    }
+
+    let (nonce, ciphertext) = payload.split_at(24);
+    let cipher = XChaCha20Poly1305::new(key.into());
+    let plaintext = cipher
+        .decrypt(XNonce::from_slice(nonce), ciphertext)
+        .map_err(|_| "Failed to decrypt content (wrong key?)".to_string())?;
+    String::from_utf8(plaintext).map_err(|e| format!("Decrypted content is not UTF-8: {}", e))
+}
+
+/// 校验口令：能用派生密钥解出校验串才算正确，返回密钥
+fn verify_passphrase(passphrase: &str, config: &EncryptionConfig) -> Result<[u8; 32], String> {
+    use base64::Engine;
+    let salt = base64::engine::general_purpose::STANDARD
+        .decode(&config.salt)
+        .map_err(|e| format!("Failed to decode encryption salt: {}", e))?;
+    let key = derive_encryption_key(passphrase, &salt, config.m_cost, config.t_cost, config.p_cost)?;
+    match decrypt_content(&key, &config.verifier) {
+        Ok(check) if check == ENCRYPTION_CHECK => Ok(key),
+        _ => Err("Incorrect passphrase".to_string()),
+    }
+}
+
+/// 开启加密：生成盐、派生密钥并把已有的明文文本就地加密。
+/// content_hash 保持明文哈希不变，随机 nonce 不影响去重
+pub fn enable_clipboard_encryption(
+    passphrase: &str,
+    app_data_dir: &PathBuf,
+) -> Result<(), String> {
+    if passphrase.is_empty() {
+        return Err("Passphrase must not be empty".to_string());
     }
 
     let conn = db::get_connection(app_data_dir)?;
-    
-    // 统计非收藏项的数量
-    let non_favorite_count: i64 = conn
-        .query_row(
-            "SELECT COUNT(*) FROM clipboard_history WHERE is_favorite = 0",
-            [],
-            |row| row.get(0),
-        )
-        .map_err(|e| format!("Failed to count clipboard items: {}", e))?;
-    
-    if non_favorite_count <= max_items as i64 {
-        // 未超过最大数量，不需要删除
-        return Ok(());
+    if clipboard_encryption_enabled(&conn) {
+        return Err("Clipboard encryption is already enabled".to_string());
     }
-    
-    // 计算需要删除的数量
-    let to_delete = non_favorite_count - max_items as i64;
-    
-    // 查询最旧的非收藏项（按创建时间升序）
+
+    let mut salt = [0u8; 16];
+    {
+        use chacha20poly1305::aead::rand_core::RngCore;
+        chacha20poly1305::aead::OsRng.fill_bytes(&mut salt);
+    }
+    let params = argon2::Params::DEFAULT;
+    let (m_cost, t_cost, p_cost) = (params.m_cost(), params.t_cost(), params.p_cost());
+    let key = derive_encryption_key(passphrase, &salt, m_cost, t_cost, p_cost)?;
+
+    use base64::Engine;
+    let config = EncryptionConfig {
+        enabled: true,
+        salt: base64::engine::general_purpose::STANDARD.encode(salt),
+        m_cost,
+        t_cost,
+        p_cost,
+        verifier: encrypt_content(&key, ENCRYPTION_CHECK)?,
+    };
+    save_encryption_config(&conn, &config)?;
+
+    // 已有的明文文本（含表格）逐条加密；标题可能泄露内容前缀，一并清掉
     let mut stmt = conn
-        .prepare(
-            "SELECT id, content, content_type 
-             FROM clipboard_history 
-             WHERE is_favorite = 0 
-             ORDER BY created_at ASC 
-             LIMIT ?1"
-        )
-        .map_err(|e| format!("Failed to prepare delete query: {}", e))?;
-    
-    let items_to_delete: Vec<(String, String, String)> = stmt
-        .query_map(params![to_delete], |row| {
-            Ok((row.get(0)?, row.get(1)?, row.get(2)?))
-        })
-        .map_err(|e| format!("Failed to query items to delete: {}", e))?
-        .filter_map(|r| r.ok())
-        .collect();
-    
+        .prepare("SELECT id, content, raw_content FROM clipboard_history WHERE content_type IN ('text', 'table')")
+        .map_err(|e| format!("Failed to prepare encryption query: {}", e))?;
+    let rows: Vec<(String, String, Option<String>)> = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+        .map_err(|e| format!("Failed to query text items: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to read text items: {}", e))?;
     drop(stmt);
-    
-    // 删除对应的图片文件
-    for (id, content, content_type) in &items_to_delete {
-        if content_type == "image" {
-            let image_path = std::path::Path::new(content);
-            if image_path.exists() {
-                // 检查是否还有其他记录引用这个图片
-                let ref_count: i64 = conn
-                    .query_row(
-                        "SELECT COUNT(*) FROM clipboard_history WHERE content = ?1 AND content_type = 'image'",
-                        params![content],
-                        |row| row.get(0),
-                    )
-                    .unwrap_or(0);
-                
-                // 只有当没有其他记录引用时才删除文件
-                if ref_count <= 1 {
-                    if let Err(e) = std::fs::remove_file(image_path) {
-                        eprintln!("[Clipboard] Failed to delete image file {}: {}", content, e);
-                    } else {
-                        println!("[Clipboard] Deleted image file: {}", content);
-                    }
-                }
-            }
+
+    for (id, content, raw_content) in rows {
+        if is_encrypted_content(&content) {
+            continue;
         }
-    }
-    
-    // 删除数据库记录（逐个删除更安全）
-    for (id, _, _) in &items_to_delete {
+        let enc = encrypt_content(&key, &content)?;
+        let enc_raw = match raw_content {
+            Some(raw) => Some(encrypt_content(&key, &raw)?),
+            None => None,
+        };
         conn.execute(
-            "DELETE FROM clipboard_history WHERE id = ?1",
-            params![id],
+            "UPDATE clipboard_history SET content = ?1, raw_content = ?2, title = NULL WHERE id = ?3",
+            params![enc, enc_raw, id],
         )
-        .map_err(|e| format!("Failed to delete clipboard item {}: {}", id, e))?;
+        .map_err(|e| format!("Failed to encrypt clipboard item: {}", e))?;
+    }
+
+    if let Ok(mut guard) = ENCRYPTION_KEY.lock() {
+        *guard = Some(key);
     }
-    
-    println!("[Clipboard] Deleted {} old clipboard items (max_items: {})", to_delete, max_items);
-    
     Ok(())
 }
 
-/// 更新剪切板项内容
-pub fn update_clipboard_item(
-    id: String,
-    content: String,
+/// 关闭加密：校验口令后把全部密文解回明文并删除配置
+pub fn disable_clipboard_encryption(
+    passphrase: &str,
     app_data_dir: &PathBuf,
-) -> Result<ClipboardItem, String> {
+) -> Result<(), String> {
     let conn = db::get_connection(app_data_dir)?;
+    let config = load_encryption_config(&conn)?
+        .filter(|c| c.enabled)
+        .ok_or_else(|| "Clipboard encryption is not enabled".to_string())?;
+    let key = verify_passphrase(passphrase, &config)?;
 
-    let existing: Option<ClipboardItem> = conn
-        .query_row(
-            "SELECT id, content, content_type, created_at, is_favorite FROM clipboard_history WHERE id = ?1",
-            params![id],
-            |row| {
-                Ok(ClipboardItem {
-                    id: row.get(0)?,
-                    content: row.get(1)?,
-                    content_type: row.get(2)?,
-                    created_at: row.get::<_, i64>(3)? as u64,
-                    is_favorite: row.get::<_, i64>(4)? != 0,
-                })
-            },
-        )
-        .optional()
-        .map_err(|e| format!("Failed to load clipboard item: {}", e))?;
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT id, content, raw_content, content_type FROM clipboard_history WHERE content LIKE '{}%'",
+            ENCRYPTED_PREFIX
+        ))
+        .map_err(|e| format!("Failed to prepare decryption query: {}", e))?;
+    let rows: Vec<(String, String, Option<String>, String)> = stmt
+        .query_map([], |row| {
+            Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+        })
+        .map_err(|e| format!("Failed to query encrypted items: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to read encrypted items: {}", e))?;
+    drop(stmt);
 
-    let mut item = existing.ok_or_else(|| format!("Clipboard item {} not found", id))?;
-    item.content = content;
+    for (id, content, raw_content, content_type) in rows {
+        let plain = decrypt_content(&key, &content)?;
+        let plain_raw = match raw_content {
+            Some(raw) if is_encrypted_content(&raw) => Some(decrypt_content(&key, &raw)?),
+            other => other,
+        };
+        let title = compute_title(&plain, &content_type);
+        conn.execute(
+            "UPDATE clipboard_history SET content = ?1, raw_content = ?2, title = ?3 WHERE id = ?4",
+            params![plain, plain_raw, title, id],
+        )
+        .map_err(|e| format!("Failed to decrypt clipboard item: {}", e))?;
+    }
 
     conn.execute(
-        "UPDATE clipboard_history SET content = ?1 WHERE id = ?2",
-        params![item.content, item.id],
+        "DELETE FROM settings WHERE key = ?1",
+        params![ENCRYPTION_CONFIG_KEY],
     )
-    .map_err(|e| format!("Failed to update clipboard item: {}", e))?;
+    .map_err(|e| format!("Failed to delete encryption config: {}", e))?;
 
-    Ok(item)
+    lock_clipboard_encryption();
+    Ok(())
 }
 
-/// 切换收藏状态
-pub fn toggle_favorite_clipboard_item(
-    id: String,
+/// 用口令解锁：校验通过后密钥驻留内存，读写恢复透明加解密
+pub fn unlock_clipboard_encryption(
+    passphrase: &str,
     app_data_dir: &PathBuf,
-) -> Result<ClipboardItem, String> {
-    let conn = db::get_connection(app_data_dir)?;
+) -> Result<(), String> {
+    let conn = db::get_readonly_connection(app_data_dir)?;
+    let config = load_encryption_config(&conn)?
+        .filter(|c| c.enabled)
+        .ok_or_else(|| "Clipboard encryption is not enabled".to_string())?;
+    let key = verify_passphrase(passphrase, &config)?;
 
-    let existing: Option<ClipboardItem> = conn
-        .query_row(
-            "SELECT id, content, content_type, created_at, is_favorite FROM clipboard_history WHERE id = ?1",
-            params![id],
-            |row| {
-                Ok(ClipboardItem {
-                    id: row.get(0)?,
-                    content: row.get(1)?,
-                    content_type: row.get(2)?,
-                    created_at: row.get::<_, i64>(3)? as u64,
-                    is_favorite: row.get::<_, i64>(4)? != 0,
-                })
-            },
-        )
-        .optional()
-        .map_err(|e| format!("Failed to load clipboard item: {}", e))?;
+    if let Ok(mut guard) = ENCRYPTION_KEY.lock() {
+        *guard = Some(key);
+    }
+    Ok(())
+}
 
-    let mut item = existing.ok_or_else(|| format!("Clipboard item {} not found", id))?;
-    item.is_favorite = !item.is_favorite;
+/// 锁定：清掉内存中的密钥，之后读取只能看到占位内容
+pub fn lock_clipboard_encryption() {
+    if let Ok(mut guard) = ENCRYPTION_KEY.lock() {
+        *guard = None;
+    }
+}
 
-    conn.execute(
-        "UPDATE clipboard_history SET is_favorite = ?1 WHERE id = ?2",
-        params![if item.is_favorite { 1 } else { 0 }, item.id],
-    )
-    .map_err(|e| format!("Failed to toggle favorite: {}", e))?;
+pub fn get_clipboard_encryption_status(
+    app_data_dir: &PathBuf,
+) -> Result<ClipboardEncryptionStatus, String> {
+    let conn = db::get_readonly_connection(app_data_dir)?;
+    let enabled = clipboard_encryption_enabled(&conn);
+    Ok(ClipboardEncryptionStatus {
+        enabled,
+        locked: enabled && current_encryption_key().is_none(),
+    })
+}
 
-    Ok(item)
+/// 读取侧统一处理加密条目：已解锁则解密，锁定则替换为占位内容
+fn apply_decryption(items: &mut [ClipboardItem]) {
+    let key = current_encryption_key();
+    for item in items.iter_mut() {
+        if !is_encrypted_content(&item.content) {
+            continue;
+        }
+        match &key {
+            Some(key) => {
+                if let Ok(plain) = decrypt_content(key, &item.content) {
+                    item.content = plain;
+                }
+                if let Some(raw) = &item.raw_content {
+                    if is_encrypted_content(raw) {
+                        if let Ok(plain) = decrypt_content(key, raw) {
+                            item.raw_content = Some(plain);
+                        }
+                    }
+                }
+            }
+            None => {
+                item.content = LOCKED_PLACEHOLDER.to_string();
+                item.raw_content = None;
+            }
+        }
+    }
 }
 
-/// 删除剪切板项
-pub fn delete_clipboard_item(id: String, app_data_dir: &PathBuf) -> Result<(), String> {
-    let conn = db::get_connection(app_data_dir)?;
-    
-    // 先查询该项的内容和类型，如果是图片则需要删除文件
-    let item: Option<(String, String)> = conn
-        .query_row(
-            "SELECT content, content_type FROM clipboard_history WHERE id = ?1",
-            params![id],
-            |row| Ok((row.get(0)?, row.get(1)?)),
-        )
-        .optional()
-        .map_err(|e| format!("Failed to query clipboard item: {}", e))?;
-    
-    // 先删除数据库记录
+/// 需要真实明文的输出路径（复制、导出、链接提取）统一走这里：
+/// 锁定时直接报错，而不是把密文或占位符送出去
+fn decrypt_for_output(item: &mut ClipboardItem) -> Result<(), String> {
+    if !is_encrypted_content(&item.content) {
+        return Ok(());
+    }
+    let key = current_encryption_key().ok_or_else(|| "Clipboard encryption is locked".to_string())?;
+    item.content = decrypt_content(&key, &item.content)?;
+    if let Some(raw) = &item.raw_content {
+        if is_encrypted_content(raw) {
+            item.raw_content = Some(decrypt_content(&key, raw)?);
+        }
+    }
+    Ok(())
+}
+
+/// 监控日志统一出口：输出结构化 JSON（级别、错误类别、内容类型、消息）
+pub fn monitor_log(level: LogLevel, category: &str, content_type: Option<&str>, msg: &str) {
+    let entry = serde_json::json!({
+        "ts": now_ts(),
+        "level": level,
+        "category": category,
+        "content_type": content_type,
+        "message": msg,
+    });
+    let line = entry.to_string();
+
+    if let Some(hook) = MONITOR_LOG_HOOK.get() {
+        hook(level, &line);
+    } else {
+        crate::logger::write_log("ClipboardMonitor", &line);
+    }
+}
+
+fn now_ts() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+static ID_COUNTER: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(0);
+
+/// 生成类型自述且防碰撞的 id，如 "text-1700000000-1a2b3c"
+/// 纳秒加进程内计数器组合，同一秒内连续插入也不会撞 id
+pub fn new_item_id(content_type: &str) -> String {
+    let now = now_ts();
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .subsec_nanos();
+    let count = ID_COUNTER.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+    format!("{}-{}-{:x}{:x}", content_type, now, nanos, count)
+}
+
+/// 分页查询结果：一页数据加上指向下一页的游标
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClipboardPage {
+    pub items: Vec<ClipboardItem>,
+    /// 不透明游标，传回即可取下一页；None 表示没有更多数据
+    pub next_cursor: Option<String>,
+}
+
+/// 将最后一项编码为不透明游标（created_at + id，保证键集分页稳定）
+fn encode_cursor(item: &ClipboardItem) -> String {
+    use base64::Engine;
+    base64::engine::general_purpose::STANDARD.encode(format!("{}:{}", item.created_at, item.id))
+}
+
+/// 解析游标，返回 (created_at, id)
+fn decode_cursor(cursor: &str) -> Result<(i64, String), String> {
+    use base64::Engine;
+    let raw = base64::engine::general_purpose::STANDARD
+        .decode(cursor)
+        .map_err(|e| format!("Invalid cursor: {}", e))?;
+    let raw = String::from_utf8(raw).map_err(|e| format!("Invalid cursor: {}", e))?;
+    let (ts, id) = raw
+        .split_once(':')
+        .ok_or_else(|| "Invalid cursor format".to_string())?;
+    let ts: i64 = ts
+        .parse()
+        .map_err(|e| format!("Invalid cursor timestamp: {}", e))?;
+    Ok((ts, id.to_string()))
+}
+
+/// 计算内容的 SHA-256 十六进制哈希
+fn content_hash(content: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(content.as_bytes());
+    format!("{:x}", hasher.finalize())
+}
+
+/// 计算图片文件像素数据所在文件的 SHA-256 哈希（读不到文件时返回 None）
+fn image_file_hash(path: &str) -> Option<String> {
+    let bytes = std::fs::read(path).ok()?;
+    let mut hasher = Sha256::new();
+    hasher.update(&bytes);
+    Some(format!("{:x}", hasher.finalize()))
+}
+
+/// 缩略图放在原图旁边，文件名加 _thumb 后缀
+fn thumbnail_path_for(image_path: &str) -> std::path::PathBuf {
+    let path = std::path::Path::new(image_path);
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+    path.with_file_name(format!("{}_thumb.png", stem))
+}
+
+/// 删除图片文件时连同缩略图一起清理
+fn remove_thumbnail_for(image_path: &str) {
+    let thumb = thumbnail_path_for(image_path);
+    if thumb.exists() {
+        let _ = std::fs::remove_file(thumb);
+    }
+}
+
+/// 为图片生成缩略图（长边压到 256px，邻近采样），写到原图旁的 *_thumb.png
+/// 原图本身足够小时不生成，返回 None
+fn generate_thumbnail(image_path: &str) -> Result<Option<String>, String> {
+    const MAX_EDGE: u32 = 256;
+
+    let file =
+        std::fs::File::open(image_path).map_err(|e| format!("Failed to open image: {}", e))?;
+    let decoder = png::Decoder::new(file);
+    let mut reader = decoder
+        .read_info()
+        .map_err(|e| format!("Failed to read PNG: {}", e))?;
+    let mut buf = vec![0u8; reader.output_buffer_size()];
+    let info = reader
+        .next_frame(&mut buf)
+        .map_err(|e| format!("Failed to decode PNG: {}", e))?;
+
+    let (width, height) = (info.width, info.height);
+    if width.max(height) <= MAX_EDGE {
+        return Ok(None);
+    }
+
+    // 统一成 RGBA 再采样
+    let rgba: Vec<u8> = match info.color_type {
+        png::ColorType::Rgba => buf[..info.buffer_size()].to_vec(),
+        png::ColorType::Rgb => {
+            let mut out = Vec::with_capacity((width * height * 4) as usize);
+            for px in buf[..info.buffer_size()].chunks_exact(3) {
+                out.extend_from_slice(&[px[0], px[1], px[2], 255]);
+            }
+            out
+        }
+        other => return Err(format!("Unsupported PNG color type: {:?}", other)),
+    };
+
+    let scale = MAX_EDGE as f64 / width.max(height) as f64;
+    let thumb_w = ((width as f64 * scale).round() as u32).max(1);
+    let thumb_h = ((height as f64 * scale).round() as u32).max(1);
+
+    let mut out = Vec::with_capacity((thumb_w * thumb_h * 4) as usize);
+    for y in 0..thumb_h {
+        let src_y = (y as u64 * height as u64 / thumb_h as u64) as usize;
+        for x in 0..thumb_w {
+            let src_x = (x as u64 * width as u64 / thumb_w as u64) as usize;
+            let i = (src_y * width as usize + src_x) * 4;
+            out.extend_from_slice(&rgba[i..i + 4]);
+        }
+    }
+
+    let thumb_path = thumbnail_path_for(image_path);
+    let file = std::fs::File::create(&thumb_path)
+        .map_err(|e| format!("Failed to create thumbnail file: {}", e))?;
+    let mut encoder = png::Encoder::new(std::io::BufWriter::new(file), thumb_w, thumb_h);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder
+        .write_header()
+        .map_err(|e| format!("Failed to write thumbnail header: {}", e))?;
+    writer
+        .write_image_data(&out)
+        .map_err(|e| format!("Failed to write thumbnail data: {}", e))?;
+
+    Ok(Some(thumb_path.to_string_lossy().to_string()))
+}
+
+/// 读取内容屏蔽哈希列表（settings 表中只存哈希，不存明文）
+fn load_blocklist(app_data_dir: &PathBuf) -> Result<Vec<String>, String> {
+    let conn = db::get_connection(app_data_dir)?;
+
+    let value: Option<String> = conn
+        .query_row(
+            "SELECT value FROM settings WHERE key = 'clipboard_blocklist' LIMIT 1",
+            [],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| format!("Failed to load clipboard blocklist: {}", e))?;
+
+    if let Some(json) = value {
+        serde_json::from_str(&json)
+            .map_err(|e| format!("Failed to parse clipboard blocklist: {}", e))
+    } else {
+        Ok(Vec::new())
+    }
+}
+
+/// 判断内容是否命中屏蔽哈希列表
+pub fn content_matches_blocklist(content: &str, app_data_dir: &PathBuf) -> Result<bool, String> {
+    let blocklist = load_blocklist(app_data_dir)?;
+    if blocklist.is_empty() {
+        return Ok(false);
+    }
+    let hash = content_hash(content);
+    Ok(blocklist.iter().any(|h| h == &hash))
+}
+
+/// 将内容加入屏蔽列表（只保存哈希，明文不落盘）
+pub fn add_to_blocklist(content: &str, app_data_dir: &PathBuf) -> Result<(), String> {
+    let mut blocklist = load_blocklist(app_data_dir)?;
+    let hash = content_hash(content);
+    if blocklist.iter().any(|h| h == &hash) {
+        return Ok(());
+    }
+    blocklist.push(hash);
+
+    let json = serde_json::to_string(&blocklist)
+        .map_err(|e| format!("Failed to serialize clipboard blocklist: {}", e))?;
+
+    let conn = db::get_connection(app_data_dir)?;
+    conn.execute(
+        "INSERT INTO settings (key, value) VALUES ('clipboard_blocklist', ?1)
+         ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+        params![json],
+    )
+    .map_err(|e| format!("Failed to save clipboard blocklist: {}", e))?;
+
+    Ok(())
+}
+
+/// 标题最大长度（字符数）
+const TITLE_MAX_CHARS: usize = 80;
+
+/// 计算条目的单行标题：文本取首个非空行（截断），
+/// 文件取文件名，图片读 PNG 尺寸显示 "Image 宽x高"
+pub fn compute_title(content: &str, content_type: &str) -> String {
+    match content_type {
+        "image" => match png_dimensions(content) {
+            Some((width, height)) => format!("Image {}x{}", width, height),
+            None => "Image".to_string(),
+        },
+        "file" => content
+            .lines()
+            .next()
+            .and_then(|first| {
+                std::path::Path::new(first)
+                    .file_name()
+                    .map(|n| n.to_string_lossy().to_string())
+            })
+            .unwrap_or_else(|| content.to_string()),
+        _ => content
+            .lines()
+            .find(|line| !line.trim().is_empty())
+            .unwrap_or("")
+            .trim()
+            .chars()
+            .take(TITLE_MAX_CHARS)
+            .collect(),
+    }
+}
+
+/// 读取 PNG 文件的宽高，失败返回 None
+/// 校验单个条目的内容是否与其声明类型一致
+fn content_matches_type(content: &str, content_type: &str) -> bool {
+    match content_type {
+        "image" => png_dimensions(content).is_some(),
+        "file" => {
+            let mut lines = content.lines().filter(|l| !l.trim().is_empty());
+            let mut any = false;
+            for line in lines.by_ref() {
+                any = true;
+                if !std::path::Path::new(line.trim()).exists() {
+                    return false;
+                }
+            }
+            any
+        }
+        // 文本类条目不应存放图片文件路径
+        _ => png_dimensions(content.trim()).is_none(),
+    }
+}
+
+/// 审计 content_type 与实际内容不一致的条目（图片路径失效、文本误存图片路径等）
+pub fn audit_content_types(app_data_dir: &PathBuf) -> Result<Vec<ClipboardItem>, String> {
+    let conn = db::get_connection(app_data_dir)?;
+
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM clipboard_history ORDER BY created_at DESC",
+            ITEM_COLUMNS
+        ))
+        .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+
+    let items = stmt
+        .query_map([], map_item_row)
+        .map_err(|e| format!("Failed to query clipboard items: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to read clipboard items: {}", e))?;
+
+    Ok(items
+        .into_iter()
+        .filter(|item| !content_matches_type(&item.content, &item.content_type))
+        .collect())
+}
+
+fn png_dimensions(path: &str) -> Option<(u32, u32)> {
+    let file = std::fs::File::open(path).ok()?;
+    let decoder = png::Decoder::new(std::io::BufReader::new(file));
+    let reader = decoder.read_info().ok()?;
+    let info = reader.info();
+    Some((info.width, info.height))
+}
+
+/// 保守地检测表格内容：至少两行非空文本、统一的制表符/逗号分隔、
+/// 每行列数一致且不少于两列。返回 (行数, 列数)
+fn detect_table(content: &str) -> Option<(u32, u32)> {
+    let lines: Vec<&str> = content.lines().filter(|l| !l.trim().is_empty()).collect();
+    if lines.len() < 2 {
+        return None;
+    }
+
+    // 制表符优先（Excel 复制用 \t），其次是逗号
+    for sep in ['\t', ','] {
+        let cols = lines[0].matches(sep).count() + 1;
+        if cols < 2 {
+            continue;
+        }
+        if lines.iter().all(|l| l.matches(sep).count() + 1 == cols) {
+            return Some((lines.len() as u32, cols as u32));
+        }
+    }
+
+    None
+}
+
+/// 启发式判断文本子类型，供界面提供打开链接、显示色块等智能动作。
+/// 返回 "url"、"email"、"hex_color"、"code" 或 "plain"
+pub fn detect_text_kind(content: &str) -> &'static str {
+    let trimmed = content.trim();
+    if trimmed.is_empty() {
+        return "plain";
+    }
+
+    // 单行无空白才可能是 URL / 邮箱 / 颜色值
+    if !trimmed.contains(char::is_whitespace) {
+        let lower = trimmed.to_lowercase();
+        if lower.starts_with("http://")
+            || lower.starts_with("https://")
+            || lower.starts_with("ftp://")
+            || lower.starts_with("www.")
+        {
+            return "url";
+        }
+
+        // #rgb / #rrggbb / #rrggbbaa
+        if let Some(hex) = trimmed.strip_prefix('#') {
+            if matches!(hex.len(), 3 | 6 | 8) && hex.chars().all(|c| c.is_ascii_hexdigit()) {
+                return "hex_color";
+            }
+        }
+
+        // 本地部分@域名，域名带点且不含连续的点
+        if let Some((local, domain)) = trimmed.split_once('@') {
+            if !local.is_empty()
+                && domain.contains('.')
+                && !domain.contains("..")
+                && !domain.starts_with('.')
+                && !domain.ends_with('.')
+                && !domain.contains('@')
+            {
+                return "email";
+            }
+        }
+    }
+
+    // 代码：多行且出现足够多的典型语法特征（括号配对、行尾分号、关键字）
+    let lines: Vec<&str> = trimmed.lines().collect();
+    if lines.len() >= 2 {
+        let mut score = 0;
+        if trimmed.contains('{') && trimmed.contains('}') {
+            score += 1;
+        }
+        if lines.iter().filter(|l| l.trim_end().ends_with(';')).count() >= 2 {
+            score += 1;
+        }
+        let keywords = [
+            "fn ", "function ", "def ", "class ", "import ", "#include", "pub ", "let ",
+            "const ", "var ", "return ", "if (", "for (", "while (",
+        ];
+        if lines
+            .iter()
+            .any(|l| keywords.iter().any(|k| l.trim_start().starts_with(k)))
+        {
+            score += 1;
+        }
+        if lines.iter().filter(|l| l.starts_with("    ") || l.starts_with('\t')).count()
+            >= lines.len() / 2
+        {
+            score += 1;
+        }
+        if score >= 2 {
+            return "code";
+        }
+    }
+
+    "plain"
+}
+
+/// 归一化文本内容：统一换行符为 \n 并去除首尾空白
+fn normalize_text(content: &str) -> String {
+    content
+        .replace("\r\n", "\n")
+        .replace('\r', "\n")
+        .trim()
+        .to_string()
+}
+
+/// Unicode NFC 归一化
+fn nfc_normalize(text: &str) -> String {
+    use unicode_normalization::UnicodeNormalization;
+    text.nfc().collect()
+}
+
+/// 搜索词按用户设置做同样的 NFC 归一化，保证能搜到归一化入库的内容
+fn normalize_query(query: &str, app_data_dir: &PathBuf) -> String {
+    let nfc = settings::load_settings(app_data_dir)
+        .map(|s| s.clipboard_unicode_nfc)
+        .unwrap_or(false);
+    if nfc {
+        nfc_normalize(query)
+    } else {
+        query.to_string()
+    }
+}
+
+/// 偏移分页结果：一页数据加总数，供需要页码跳转的界面使用
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClipboardOffsetPage {
+    pub items: Vec<ClipboardItem>,
+    pub total: u64,
+}
+
+/// 按 offset/limit 分页获取历史，可按类型过滤（None 表示全部）；
+/// 收藏优先，其余按时间倒序。与键集分页互补
+pub fn get_clipboard_items_offset(
+    app_data_dir: &PathBuf,
+    offset: u32,
+    limit: u32,
+    content_type_filter: Option<String>,
+) -> Result<ClipboardOffsetPage, String> {
+    let conn = db::get_readonly_connection(app_data_dir)?;
+    let limit = if limit == 0 { 50 } else { limit };
+
+    let (type_clause, count_sql) = if content_type_filter.is_some() {
+        (
+            " WHERE content_type = ?1",
+            "SELECT COUNT(*) FROM clipboard_history WHERE content_type = ?1",
+        )
+    } else {
+        ("", "SELECT COUNT(*) FROM clipboard_history")
+    };
+
+    let total: i64 = if let Some(ct) = content_type_filter.as_deref() {
+        conn.query_row(count_sql, params![ct], |row| row.get(0))
+    } else {
+        conn.query_row(count_sql, [], |row| row.get(0))
+    }
+    .map_err(|e| format!("Failed to count clipboard items: {}", e))?;
+
+    let sql = format!(
+        "SELECT {} FROM clipboard_history{}
+         ORDER BY is_favorite DESC, created_at DESC
+         LIMIT {} OFFSET {}",
+        ITEM_COLUMNS, type_clause, limit, offset
+    );
+
+    let mut stmt = conn
+        .prepare(&sql)
+        .map_err(|e| format!("Failed to prepare clipboard page query: {}", e))?;
+
+    let mut items = Vec::new();
+    if let Some(ct) = content_type_filter.as_deref() {
+        let rows = stmt
+            .query_map(params![ct], map_item_row)
+            .map_err(|e| format!("Failed to iterate clipboard items: {}", e))?;
+        for row in rows {
+            items.push(row.map_err(|e| format!("Failed to read clipboard row: {}", e))?);
+        }
+    } else {
+        let rows = stmt
+            .query_map([], map_item_row)
+            .map_err(|e| format!("Failed to iterate clipboard items: {}", e))?;
+        for row in rows {
+            items.push(row.map_err(|e| format!("Failed to read clipboard row: {}", e))?);
+        }
+    }
+
+    apply_previews(&mut items, app_data_dir);
+    Ok(ClipboardOffsetPage {
+        items,
+        total: total as u64,
+    })
+}
+
+/// 获取所有剪切板历史（兼容旧接口：一次取一大页）
+pub fn get_all_clipboard_items(app_data_dir: &PathBuf) -> Result<Vec<ClipboardItem>, String> {
+    Ok(get_clipboard_items_offset(app_data_dir, 0, u32::MAX, None)?.items)
+}
+
+/// 添加剪切板项
+pub fn add_clipboard_item(
+    content: String,
+    content_type: String,
+    app_data_dir: &PathBuf,
+) -> Result<ClipboardItem, String> {
+    add_clipboard_item_impl(content, content_type, app_data_dir, false, None).map(|(item, _)| item)
+}
+
+/// 跳过去重直接新建一条（"另存为新条目"），默认入口仍然走去重
+pub fn add_clipboard_item_force(
+    content: String,
+    content_type: String,
+    app_data_dir: &PathBuf,
+) -> Result<ClipboardItem, String> {
+    add_clipboard_item_impl(content, content_type, app_data_dir, true, None).map(|(item, _)| item)
+}
+
+/// 按指定时间入库（导入历史数据时保留原始时间顺序），仍然走去重
+pub fn add_clipboard_item_at(
+    content: String,
+    content_type: String,
+    created_at: u64,
+    app_data_dir: &PathBuf,
+) -> Result<ClipboardItem, String> {
+    add_clipboard_item_impl(content, content_type, app_data_dir, false, Some(created_at))
+        .map(|(item, _)| item)
+}
+
+/// 返回条目和是否命中了去重（true 表示返回的是历史里已有的那条）
+fn add_clipboard_item_impl(
+    content: String,
+    content_type: String,
+    app_data_dir: &PathBuf,
+    force: bool,
+    created_at: Option<u64>,
+) -> Result<(ClipboardItem, bool), String> {
+    let now = created_at.unwrap_or_else(now_ts);
+
+    // 归一化开启时，归一化后的内容用于去重和显示，原始内容保留用于精确还原
+    let settings = settings::load_settings(app_data_dir).unwrap_or_default();
+    let (content, mut raw_content) = if content_type == "text" {
+        let mut normalized = content.clone();
+        if settings.clipboard_normalize_text {
+            normalized = normalize_text(&normalized);
+        }
+        // NFC 归一化：不同来源的等价字符（NFC/NFD）落到同一字节串
+        if settings.clipboard_unicode_nfc {
+            normalized = nfc_normalize(&normalized);
+        }
+        if normalized != content {
+            (normalized, Some(content))
+        } else {
+            (content, None)
+        }
+    } else {
+        (content, None)
+    };
+
+    // 超大文本按字节上限截断入库（按字符边界回退），后续去重针对截断后的内容
+    let mut truncated = false;
+    let content = if content_type == "text"
+        && settings.clipboard_max_text_bytes > 0
+        && content.len() > settings.clipboard_max_text_bytes as usize
+    {
+        let mut end = settings.clipboard_max_text_bytes as usize;
+        while end > 0 && !content.is_char_boundary(end) {
+            end -= 1;
+        }
+        truncated = true;
+        // 截断后不再保留归一化前的原文，否则 raw_content 会绕过字节上限
+        raw_content = None;
+        content[..end].to_string()
+    } else {
+        content
+    };
+
+    // 表格检测：规整的制表符/逗号分隔数据按 "table" 类型入库
+    let mut content_type = content_type;
+    let mut table_dims: Option<(u32, u32)> = None;
+    if content_type == "text" {
+        if let Some(dims) = detect_table(&content) {
+            content_type = "table".to_string();
+            table_dims = Some(dims);
+        }
+    }
+
+    // 文本子类型分类，入库后供界面提供智能动作
+    let kind = if content_type == "text" {
+        Some(detect_text_kind(&content).to_string())
+    } else {
+        None
+    };
+
+    let id = new_item_id(&content_type);
+    let title = compute_title(&content, &content_type);
+
+    // 图片项记录存储格式和磁盘大小
+    let (mime_type, image_bytes) = if content_type == "image" {
+        (
+            Some("image/png".to_string()),
+            std::fs::metadata(&content).ok().map(|m| m.len()),
+        )
+    } else {
+        (None, None)
+    };
+
+    // 命中屏蔽列表的内容直接丢弃，不入库
+    if content_matches_blocklist(&content, app_data_dir)? {
+        return Err("Content is blocklisted".to_string());
+    }
+
+    let conn = db::get_connection(app_data_dir)?;
+
+    // 加密开启时文本（含检测出的表格）以密文入库（哈希仍取明文，随机 nonce 不影响去重）；
+    // 密钥锁定时无法加密，为避免明文落盘直接拒绝入库
+    let plain_hash = content_hash(&content);
+    let mut encrypted = false;
+    let (stored_content, stored_raw) = if is_encryptable_type(&content_type)
+        && clipboard_encryption_enabled(&conn)
+    {
+        match current_encryption_key() {
+            Some(key) => {
+                encrypted = true;
+                let enc = encrypt_content(&key, &content)?;
+                let enc_raw = match &raw_content {
+                    Some(raw) => Some(encrypt_content(&key, raw)?),
+                    None => None,
+                };
+                (enc, enc_raw)
+            }
+            None => return Err("Clipboard encryption is locked".to_string()),
+        }
+    } else {
+        (content.clone(), raw_content.clone())
+    };
+
+    let item = ClipboardItem {
+        id: id.clone(),
+        content: content.clone(),
+        content_type: content_type.clone(),
+        created_at: now,
+        is_favorite: false,
+        raw_content: raw_content.clone(),
+        source_app: None,
+        note: None,
+        dominant_color: None,
+        table_rows: table_dims.map(|d| d.0),
+        table_cols: table_dims.map(|d| d.1),
+        group_id: None,
+        title: Some(title),
+        tags: Vec::new(),
+        mime_type: mime_type.clone(),
+        image_bytes,
+        profile: None,
+        thumbnail_path: None,
+        truncated,
+        kind: kind.clone(),
+        rich_content: None,
+        rich_format: None,
+        preview: None,
+    };
+
+    // 图片按文件字节的哈希去重：路径不同但内容相同的 PNG 视为重复
+    let image_hash = if content_type == "image" {
+        image_file_hash(&content)
+    } else {
+        None
+    };
+
+    // 检查是否已存在相同内容（避免重复）；force 时跳过，总是插入新行
+    // 配置了指纹正则的文本类内容按指纹比较，只差易变部分的内容视为重复
+    let fingerprint_patterns = &settings.clipboard_fingerprint_patterns;
+    let existing: Option<String> = if force {
+        None
+    } else if let Some(hash) = image_hash.as_deref() {
+        conn.query_row(
+            "SELECT id FROM clipboard_history WHERE content_type = 'image' AND content_hash = ?1",
+            params![hash],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| format!("Failed to check existing clipboard: {}", e))?
+    } else if encrypted {
+        // 密文每次随机，改按明文哈希判重
+        conn.query_row(
+            "SELECT id FROM clipboard_history WHERE content_type = ?1 AND content_hash = ?2",
+            params![content_type, plain_hash],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| format!("Failed to check existing clipboard: {}", e))?
+    } else if !fingerprint_patterns.is_empty() && content_type != "image" && content_type != "file"
+    {
+        let fp = fingerprint(&content, fingerprint_patterns);
+        let mut stmt = conn
+            .prepare("SELECT id, content FROM clipboard_history WHERE content_type = ?1")
+            .map_err(|e| format!("Failed to prepare dedup query: {}", e))?;
+        let rows: Vec<(String, String)> = stmt
+            .query_map(params![content_type], |row| Ok((row.get(0)?, row.get(1)?)))
+            .map_err(|e| format!("Failed to check existing clipboard: {}", e))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to read existing clipboard: {}", e))?;
+        drop(stmt);
+        rows.into_iter()
+            .find(|(_, c)| fingerprint(c, fingerprint_patterns) == fp)
+            .map(|(id, _)| id)
+    } else {
+        conn.query_row(
+            "SELECT id FROM clipboard_history WHERE content = ?1 AND content_type = ?2",
+            params![content, content_type],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| format!("Failed to check existing clipboard: {}", e))?
+    };
+
+    if let Some(existing_id) = existing {
+        // 重复图片保留已入库的文件路径；刚写入的重复 PNG 不再被引用时从磁盘删掉
+        let mut content = content;
+        if content_type == "image" {
+            let stored: Option<String> = conn
+                .query_row(
+                    "SELECT content FROM clipboard_history WHERE id = ?1",
+                    params![existing_id],
+                    |row| row.get(0),
+                )
+                .optional()
+                .map_err(|e| format!("Failed to read existing image path: {}", e))?;
+
+            if let Some(stored_path) = stored {
+                if stored_path != content {
+                    let ref_count: i64 = conn
+                        .query_row(
+                            "SELECT COUNT(*) FROM clipboard_history WHERE content = ?1 AND content_type = 'image'",
+                            params![content],
+                            |row| row.get(0),
+                        )
+                        .unwrap_or(0);
+
+                    if ref_count == 0 {
+                        let _ = std::fs::remove_file(&content);
+                    }
+                    content = stored_path;
+                }
+            }
+        }
+
+        // 如果已存在，更新时间戳和原始内容（以最近一次复制为准）
+        conn.execute(
+            "UPDATE clipboard_history SET created_at = ?1, raw_content = ?2 WHERE id = ?3",
+            params![now as i64, stored_raw, existing_id],
+        )
+        .map_err(|e| format!("Failed to update clipboard timestamp: {}", e))?;
+
+        let title = compute_title(&content, &content_type);
+        return Ok((
+            ClipboardItem {
+                id: existing_id,
+                content,
+                content_type,
+                created_at: now,
+                is_favorite: false,
+                raw_content,
+                source_app: None,
+                note: None,
+                dominant_color: None,
+                table_rows: table_dims.map(|d| d.0),
+                table_cols: table_dims.map(|d| d.1),
+                group_id: None,
+                title: Some(title),
+                tags: Vec::new(),
+                mime_type,
+                image_bytes,
+                profile: None,
+                thumbnail_path: None,
+                truncated,
+                kind,
+                rich_content: None,
+                rich_format: None,
+                preview: None,
+            },
+            true,
+        ));
+    }
+
+    conn.execute(
+        "INSERT INTO clipboard_history (id, content, content_type, created_at, is_favorite, raw_content, table_rows, table_cols, title, mime_type, image_bytes, content_hash, truncated, kind)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)",
+        params![
+            item.id,
+            stored_content,
+            item.content_type,
+            item.created_at as i64,
+            0,
+            stored_raw,
+            item.table_rows,
+            item.table_cols,
+            // 标题由明文推导，加密条目不落库避免泄露内容前缀
+            if encrypted { None } else { item.title.clone() },
+            item.mime_type,
+            item.image_bytes.map(|v| v as i64),
+            image_hash.unwrap_or(plain_hash),
+            item.truncated,
+            item.kind
+        ],
+    )
+    .map_err(|e| format!("Failed to insert clipboard item: {}", e))?;
+
+    // 大图生成缩略图，列表渲染不必加载原图；失败不影响入库
+    let mut item = item;
+    if item.content_type == "image" {
+        match generate_thumbnail(&item.content) {
+            Ok(Some(thumb)) => {
+                conn.execute(
+                    "UPDATE clipboard_history SET thumbnail_path = ?1 WHERE id = ?2",
+                    params![thumb, item.id],
+                )
+                .map_err(|e| format!("Failed to record thumbnail path: {}", e))?;
+                item.thumbnail_path = Some(thumb);
+            }
+            Ok(None) => {}
+            Err(e) => eprintln!("[Clipboard] Failed to generate thumbnail: {}", e),
+        }
+    }
+
+    // 按数量和保留时间清理历史
+    prune_clipboard_history(
+        app_data_dir,
+        settings.clipboard_max_items,
+        settings.clipboard_max_age_secs,
+    )?;
+    enforce_per_type_caps(app_data_dir)?;
+
+    // 图片另有磁盘占用预算
+    if item.content_type == "image" {
+        enforce_image_budget(app_data_dir)?;
+    }
+
+    // 全局预算兜底（数据库 + 图片合计）
+    enforce_total_budget(app_data_dir)?;
+
+    Ok((item, false))
+}
+
+/// 按类型独立限制保留数量（文本/图片/文件各一个上限），
+/// 超出的部分淘汰最旧的非收藏项，图片文件不再被引用时一并删除
+fn enforce_per_type_caps(app_data_dir: &PathBuf) -> Result<(), String> {
+    let settings = settings::load_settings(app_data_dir).unwrap_or_default();
+
+    let caps: [(&str, Option<u32>); 3] = [
+        ("text", settings.clipboard_cap_text),
+        ("image", settings.clipboard_cap_image),
+        ("file", settings.clipboard_cap_file),
+    ];
+
+    let conn = db::get_connection(app_data_dir)?;
+
+    for (content_type, cap) in caps {
+        let cap = match cap {
+            Some(cap) => cap,
+            // 未设置表示该类型不限制
+            None => continue,
+        };
+
+        let count: i64 = conn
+            .query_row(
+                "SELECT COUNT(*) FROM clipboard_history WHERE content_type = ?1 AND is_favorite = 0",
+                params![content_type],
+                |row| row.get(0),
+            )
+            .map_err(|e| format!("Failed to count {} items: {}", content_type, e))?;
+
+        if count <= cap as i64 {
+            continue;
+        }
+
+        let to_delete = count - cap as i64;
+
+        let mut stmt = conn
+            .prepare(
+                "SELECT id, content FROM clipboard_history
+                 WHERE content_type = ?1 AND is_favorite = 0
+                 ORDER BY created_at ASC
+                 LIMIT ?2",
+            )
+            .map_err(|e| format!("Failed to prepare per-type eviction query: {}", e))?;
+
+        let items_to_delete: Vec<(String, String)> = stmt
+            .query_map(params![content_type, to_delete], |row| {
+                Ok((row.get(0)?, row.get(1)?))
+            })
+            .map_err(|e| format!("Failed to query per-type eviction items: {}", e))?
+            .filter_map(|r| r.ok())
+            .collect();
+
+        drop(stmt);
+
+        for (id, content) in &items_to_delete {
+            conn.execute("DELETE FROM clipboard_history WHERE id = ?1", params![id])
+                .map_err(|e| format!("Failed to delete clipboard item {}: {}", id, e))?;
+
+            if content_type == "image" {
+                let ref_count: i64 = conn
+                    .query_row(
+                        "SELECT COUNT(*) FROM clipboard_history WHERE content = ?1 AND content_type = 'image'",
+                        params![content],
+                        |row| row.get(0),
+                    )
+                    .unwrap_or(0);
+
+                if ref_count == 0 {
+                    let path = std::path::Path::new(content);
+                    if path.exists() {
+                        if let Err(e) = std::fs::remove_file(path) {
+                            eprintln!(
+                                "[Clipboard] Failed to delete image file {}: {}",
+                                content, e
+                            );
+                        }
+                    }
+                    remove_thumbnail_for(content);
+                }
+            }
+        }
+
+        println!(
+            "[Clipboard] Evicted {} old {} items (cap: {})",
+            to_delete, content_type, cap
+        );
+    }
+
+    Ok(())
+}
+
+/// 限制图片文件的总磁盘占用：超出预算时按最旧优先淘汰非收藏的图片项
+/// （删除数据库行，文件不再被引用时一并删除）
+fn enforce_image_budget(app_data_dir: &PathBuf) -> Result<(), String> {
+    let settings = settings::load_settings(app_data_dir).unwrap_or_default();
+    let max_bytes = settings.clipboard_max_image_bytes;
+
+    if max_bytes == 0 {
+        // 0 表示不限制
+        return Ok(());
+    }
+
+    let conn = db::get_connection(app_data_dir)?;
+
+    // 统计当前所有图片文件的总大小（同一文件可能被多条记录引用，去重）
+    let mut stmt = conn
+        .prepare("SELECT DISTINCT content FROM clipboard_history WHERE content_type = 'image'")
+        .map_err(|e| format!("Failed to prepare image size query: {}", e))?;
+
+    let all_paths: Vec<String> = stmt
+        .query_map([], |row| row.get(0))
+        .map_err(|e| format!("Failed to query image paths: {}", e))?
+        .filter_map(|r| r.ok())
+        .collect();
+
+    drop(stmt);
+
+    let mut total_bytes: u64 = all_paths
+        .iter()
+        .filter_map(|p| std::fs::metadata(p).ok())
+        .map(|m| m.len())
+        .sum();
+
+    if total_bytes <= max_bytes {
+        return Ok(());
+    }
+
+    // 按最旧优先淘汰非收藏图片项
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, content FROM clipboard_history
+             WHERE content_type = 'image' AND is_favorite = 0
+             ORDER BY created_at ASC",
+        )
+        .map_err(|e| format!("Failed to prepare eviction query: {}", e))?;
+
+    let candidates: Vec<(String, String)> = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+        .map_err(|e| format!("Failed to query eviction candidates: {}", e))?
+        .filter_map(|r| r.ok())
+        .collect();
+
+    drop(stmt);
+
+    let mut evicted = 0u32;
+    for (id, content) in candidates {
+        if total_bytes <= max_bytes {
+            break;
+        }
+
+        conn.execute("DELETE FROM clipboard_history WHERE id = ?1", params![id])
+            .map_err(|e| format!("Failed to evict image item {}: {}", id, e))?;
+        evicted += 1;
+
+        // 文件不再被任何记录引用时删除并计入释放量
+        let ref_count: i64 = conn
+            .query_row(
+                "SELECT COUNT(*) FROM clipboard_history WHERE content = ?1 AND content_type = 'image'",
+                params![content],
+                |row| row.get(0),
+            )
+            .unwrap_or(0);
+
+        if ref_count == 0 {
+            let path = std::path::Path::new(&content);
+            if let Ok(metadata) = std::fs::metadata(path) {
+                if std::fs::remove_file(path).is_ok() {
+                    total_bytes = total_bytes.saturating_sub(metadata.len());
+                } else {
+                    eprintln!("[Clipboard] Failed to delete image file {}", content);
+                }
+            }
+            remove_thumbnail_for(&content);
+        }
+    }
+
+    if evicted > 0 {
+        println!(
+            "[Clipboard] Evicted {} image items to stay under {} bytes",
+            evicted, max_bytes
+        );
+    }
+
+    Ok(())
+}
+
+/// 剪切板的磁盘占用统计（数据库文件 + 图片文件）
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct StorageUsage {
+    pub db_bytes: u64,
+    pub image_bytes: u64,
+    pub total_bytes: u64,
+}
+
+/// 统计当前总占用：数据库文件大小加上所有被引用图片文件的大小（同一文件去重）
+pub fn get_storage_usage(app_data_dir: &PathBuf) -> Result<StorageUsage, String> {
+    let db_bytes = std::fs::metadata(db::get_db_path(app_data_dir))
+        .map(|m| m.len())
+        .unwrap_or(0);
+
+    let conn = db::get_connection(app_data_dir)?;
+    let mut stmt = conn
+        .prepare("SELECT DISTINCT content FROM clipboard_history WHERE content_type = 'image'")
+        .map_err(|e| format!("Failed to prepare image size query: {}", e))?;
+
+    let image_bytes: u64 = stmt
+        .query_map([], |row| row.get::<_, String>(0))
+        .map_err(|e| format!("Failed to query image paths: {}", e))?
+        .filter_map(|r| r.ok())
+        .filter_map(|p| std::fs::metadata(p).ok())
+        .map(|m| m.len())
+        .sum();
+
+    Ok(StorageUsage {
+        db_bytes,
+        image_bytes,
+        total_bytes: db_bytes + image_bytes,
+    })
+}
+
+/// 全局磁盘预算：总占用超出 clipboard_total_budget_bytes 时按最旧优先淘汰
+/// 非收藏项直到回到预算内，返回被淘汰的条目 id。
+/// 删除行不会立刻缩小数据库文件，非图片项按内容字节数估算释放量
+pub fn enforce_total_budget(app_data_dir: &PathBuf) -> Result<Vec<String>, String> {
+    let settings = settings::load_settings(app_data_dir).unwrap_or_default();
+    let budget = settings.clipboard_total_budget_bytes;
+
+    if budget == 0 {
+        // 0 表示不限制
+        return Ok(Vec::new());
+    }
+
+    let usage = get_storage_usage(app_data_dir)?;
+    if usage.total_bytes <= budget {
+        return Ok(Vec::new());
+    }
+
+    let conn = db::get_connection(app_data_dir)?;
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, content, content_type FROM clipboard_history
+             WHERE is_favorite = 0
+             ORDER BY created_at ASC",
+        )
+        .map_err(|e| format!("Failed to prepare budget eviction query: {}", e))?;
+
+    let candidates: Vec<(String, String, String)> = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+        .map_err(|e| format!("Failed to query budget eviction candidates: {}", e))?
+        .filter_map(|r| r.ok())
+        .collect();
+
+    drop(stmt);
+
+    let mut remaining = usage.total_bytes;
+    let mut evicted = Vec::new();
+
+    for (id, content, content_type) in candidates {
+        if remaining <= budget {
+            break;
+        }
+
+        conn.execute("DELETE FROM clipboard_history WHERE id = ?1", params![id])
+            .map_err(|e| format!("Failed to evict clipboard item {}: {}", id, e))?;
+
+        let mut freed = content.len() as u64;
+
+        if content_type == "image" {
+            let ref_count: i64 = conn
+                .query_row(
+                    "SELECT COUNT(*) FROM clipboard_history WHERE content = ?1 AND content_type = 'image'",
+                    params![content],
+                    |row| row.get(0),
+                )
+                .unwrap_or(0);
+
+            if ref_count == 0 {
+                let path = std::path::Path::new(&content);
+                if let Ok(metadata) = std::fs::metadata(path) {
+                    if std::fs::remove_file(path).is_ok() {
+                        freed = metadata.len();
+                    } else {
+                        eprintln!("[Clipboard] Failed to delete image file {}", content);
+                    }
+                }
+                remove_thumbnail_for(&content);
+            }
+        }
+
+        remaining = remaining.saturating_sub(freed.max(1));
+        evicted.push(id);
+    }
+
+    if !evicted.is_empty() {
+        println!(
+            "[Clipboard] Evicted {} items to stay under total budget of {} bytes",
+            evicted.len(),
+            budget
+        );
+    }
+
+    Ok(evicted)
+}
+
+/// 按数量和时间两个维度清理非收藏的历史记录：早于 max_age_secs 的记录
+/// 和超出 max_items 的最旧记录都会删除，收藏项始终保留，
+/// 图片文件不再被引用时一并删除；0 表示对应维度不限制
+pub fn prune_clipboard_history(
+    app_data_dir: &PathBuf,
+    max_items: u32,
+    max_age_secs: u64,
+) -> Result<(), String> {
+    let conn = db::get_connection(app_data_dir)?;
+
+    // 先按时间清理过期项
+    if max_age_secs > 0 {
+        let cutoff = now_ts().saturating_sub(max_age_secs) as i64;
+
+        let mut stmt = conn
+            .prepare(
+                "SELECT id, content, content_type FROM clipboard_history
+                 WHERE is_favorite = 0 AND created_at < ?1",
+            )
+            .map_err(|e| format!("Failed to prepare age prune query: {}", e))?;
+
+        let expired: Vec<(String, String, String)> = stmt
+            .query_map(params![cutoff], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+            })
+            .map_err(|e| format!("Failed to query expired items: {}", e))?
+            .filter_map(|r| r.ok())
+            .collect();
+
+        drop(stmt);
+
+        if !expired.is_empty() {
+            delete_pruned(&conn, &expired)?;
+            println!(
+                "[Clipboard] Pruned {} expired clipboard items (max_age_secs: {})",
+                expired.len(),
+                max_age_secs
+            );
+        }
+    }
+
+    // 再按数量清理超出上限的最旧项
+    if max_items > 0 {
+        let non_favorite_count: i64 = conn
+            .query_row(
+                "SELECT COUNT(*) FROM clipboard_history WHERE is_favorite = 0",
+                [],
+                |row| row.get(0),
+            )
+            .map_err(|e| format!("Failed to count clipboard items: {}", e))?;
+
+        if non_favorite_count > max_items as i64 {
+            let to_delete = non_favorite_count - max_items as i64;
+
+            let mut stmt = conn
+                .prepare(
+                    "SELECT id, content, content_type FROM clipboard_history
+                     WHERE is_favorite = 0
+                     ORDER BY created_at ASC
+                     LIMIT ?1",
+                )
+                .map_err(|e| format!("Failed to prepare count prune query: {}", e))?;
+
+            let overflow: Vec<(String, String, String)> = stmt
+                .query_map(params![to_delete], |row| {
+                    Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+                })
+                .map_err(|e| format!("Failed to query items to delete: {}", e))?
+                .filter_map(|r| r.ok())
+                .collect();
+
+            drop(stmt);
+
+            delete_pruned(&conn, &overflow)?;
+            println!(
+                "[Clipboard] Pruned {} old clipboard items (max_items: {})",
+                overflow.len(),
+                max_items
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// 删除记录并清理不再被引用的图片文件
+fn delete_pruned(
+    conn: &rusqlite::Connection,
+    items: &[(String, String, String)],
+) -> Result<(), String> {
+    for (id, content, content_type) in items {
+        conn.execute("DELETE FROM clipboard_history WHERE id = ?1", params![id])
+            .map_err(|e| format!("Failed to delete clipboard item {}: {}", id, e))?;
+
+        if content_type == "image" {
+            let ref_count: i64 = conn
+                .query_row(
+                    "SELECT COUNT(*) FROM clipboard_history WHERE content = ?1 AND content_type = 'image'",
+                    params![content],
+                    |row| row.get(0),
+                )
+                .unwrap_or(0);
+
+            if ref_count == 0 {
+                let path = std::path::Path::new(content);
+                if path.exists() {
+                    if let Err(e) = std::fs::remove_file(path) {
+                        eprintln!("[Clipboard] Failed to delete image file {}: {}", content, e);
+                    }
+                }
+                remove_thumbnail_for(content);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// 更新剪切板项内容
+pub fn update_clipboard_item(
+    id: String,
+    content: String,
+    app_data_dir: &PathBuf,
+) -> Result<ClipboardItem, String> {
+    let conn = db::get_connection(app_data_dir)?;
+
+    let existing: Option<ClipboardItem> = conn
+        .query_row(
+            &format!(
+                "SELECT {} FROM clipboard_history WHERE id = ?1",
+                ITEM_COLUMNS
+            ),
+            params![id],
+            map_item_row,
+        )
+        .optional()
+        .map_err(|e| format!("Failed to load clipboard item: {}", e))?;
+
+    let mut item = existing.ok_or_else(|| format!("Clipboard item {} not found", id))?;
+    item.content = content;
+    // 手动编辑后原始内容不再对应，一并清除；标题随内容重新推导
+    item.raw_content = None;
+    item.title = Some(compute_title(&item.content, &item.content_type));
+
+    // 编辑过的内容通常是想保留的，开启 favorite_on_edit 后自动收藏
+    let settings = settings::load_settings(app_data_dir).unwrap_or_default();
+    if settings.clipboard_favorite_on_edit {
+        item.is_favorite = true;
+    }
+
+    // 开启加密时编辑结果同样要密文落库；锁定状态下拒绝编辑，避免明文回写
+    let encrypted = is_encryptable_type(&item.content_type) && clipboard_encryption_enabled(&conn);
+    let stored_content = if encrypted {
+        match current_encryption_key() {
+            Some(key) => encrypt_content(&key, &item.content)?,
+            None => return Err("Clipboard encryption is locked".to_string()),
+        }
+    } else {
+        item.content.clone()
+    };
+
+    // 与插入路径一致：content_hash 始终记录明文哈希，供加密判重使用
+    let new_hash = if item.content_type == "image" {
+        image_file_hash(&item.content).unwrap_or_else(|| content_hash(&item.content))
+    } else {
+        content_hash(&item.content)
+    };
+
+    conn.execute(
+        "UPDATE clipboard_history SET content = ?1, raw_content = NULL, is_favorite = ?2, title = ?3, content_hash = ?4 WHERE id = ?5",
+        params![
+            stored_content,
+            if item.is_favorite { 1 } else { 0 },
+            // 标题由明文推导，加密条目不落库避免泄露内容前缀
+            if encrypted { None } else { item.title.clone() },
+            new_hash,
+            item.id
+        ],
+    )
+    .map_err(|e| format!("Failed to update clipboard item: {}", e))?;
+
+    Ok(item)
+}
+
+/// 切换收藏状态
+pub fn toggle_favorite_clipboard_item(
+    id: String,
+    app_data_dir: &PathBuf,
+) -> Result<ClipboardItem, String> {
+    let conn = db::get_connection(app_data_dir)?;
+
+    let existing: Option<ClipboardItem> = conn
+        .query_row(
+            &format!(
+                "SELECT {} FROM clipboard_history WHERE id = ?1",
+                ITEM_COLUMNS
+            ),
+            params![id],
+            map_item_row,
+        )
+        .optional()
+        .map_err(|e| format!("Failed to load clipboard item: {}", e))?;
+
+    let mut item = existing.ok_or_else(|| format!("Clipboard item {} not found", id))?;
+    item.is_favorite = !item.is_favorite;
+
+    conn.execute(
+        "UPDATE clipboard_history SET is_favorite = ?1 WHERE id = ?2",
+        params![if item.is_favorite { 1 } else { 0 }, item.id],
+    )
+    .map_err(|e| format!("Failed to toggle favorite: {}", e))?;
+
+    Ok(item)
+}
+
+/// 切换收藏后的结果：更新后的条目及重排后的列表顶部片段
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ToggleFavoriteResult {
+    pub item: ClipboardItem,
+    pub items: Vec<ClipboardItem>,
+}
+
+/// 切换收藏并在同一次往返里返回重排后的前 limit 条，
+/// 避免前端为移动一个条目而整表刷新
+pub fn toggle_favorite_and_list(
+    id: String,
+    limit: u32,
+    app_data_dir: &PathBuf,
+) -> Result<ToggleFavoriteResult, String> {
+    let item = toggle_favorite_clipboard_item(id, app_data_dir)?;
+
+    let conn = db::get_readonly_connection(app_data_dir)?;
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM clipboard_history ORDER BY is_favorite DESC, created_at DESC LIMIT ?1",
+            ITEM_COLUMNS
+        ))
+        .map_err(|e| format!("Failed to prepare clipboard query: {}", e))?;
+
+    let rows = stmt
+        .query_map(params![limit], map_item_row)
+        .map_err(|e| format!("Failed to iterate clipboard items: {}", e))?;
+
+    let mut items = Vec::new();
+    for row in rows {
+        items.push(row.map_err(|e| format!("Failed to read clipboard row: {}", e))?);
+    }
+
+    apply_previews(&mut items, app_data_dir);
+    Ok(ToggleFavoriteResult { item, items })
+}
+
+/// 删除剪切板项。找不到该 id 时返回 ClipboardError::NotFound，
+/// 调用方可按变体匹配而不用比对错误文案
+pub fn delete_clipboard_item(id: String, app_data_dir: &PathBuf) -> Result<(), ClipboardError> {
+    let conn = db::get_connection(app_data_dir)?;
+
+    // 先查询该项的内容和类型，如果是图片则需要删除文件
+    let item: Option<(String, String)> = conn
+        .query_row(
+            "SELECT content, content_type FROM clipboard_history WHERE id = ?1",
+            params![id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()?;
+
+    // 先删除数据库记录
+    let affected = conn.execute("DELETE FROM clipboard_history WHERE id = ?1", params![id])?;
+    if affected == 0 {
+        return Err(ClipboardError::NotFound);
+    }
+    
+    if let Some((content, content_type)) = item {
+        // 如果是图片类型，检查是否还有其他记录引用这个文件
+        if content_type == "image" {
+            let ref_count: i64 = conn
+                .query_row(
+                    "SELECT COUNT(*) FROM clipboard_history WHERE content = ?1 AND content_type = 'image'",
+                    params![content],
+                    |row| row.get(0),
+                )
+                .unwrap_or(0);
+            
+            println!("[Clipboard] Image {} has {} remaining references", content, ref_count);
+            
+            // 只有当没有其他记录引用时才删除文件
+            if ref_count == 0 {
+                let image_path = std::path::Path::new(&content);
+                println!("[Clipboard] Deleting image: {}", content);
+                
+                if image_path.exists() {
+                    // 重试删除最多3次
+                    let mut retry = 0;
+                    let max_retries = 3;
+                    
+                    while retry < max_retries {
+                        match std::fs::remove_file(image_path) {
+                            Ok(_) => {
+                                println!("[Clipboard] Successfully deleted image file: {}", content);
+                                break;
+                            }
+                            Err(e) => {
+                                retry += 1;
+                                eprintln!("[Clipboard] Failed to delete image file {} (attempt {}): {}", content, retry, e);
+                                if retry < max_retries {
+                                    std::thread::sleep(std::time::Duration::from_millis(100));
+                                }
+                            }
+                        }
+                    }
+                } else {
+                    eprintln!("[Clipboard] Image file not found: {}", content);
+                }
+                remove_thumbnail_for(&content);
+            }
+        }
+    }
+    
+    Ok(())
+}
+
+/// 批量删除：全部删除在一个事务里完成，任一步失败整体回滚。
+/// 返回实际删除的条数；不再被引用的图片文件在事务提交后清理
+pub fn delete_clipboard_items(
+    ids: Vec<String>,
+    app_data_dir: &PathBuf,
+) -> Result<u32, ClipboardError> {
+    if ids.is_empty() {
+        return Ok(0);
+    }
+
+    let mut conn = db::get_connection(app_data_dir)?;
+    let tx = conn.transaction()?;
+
+    let placeholders = (1..=ids.len())
+        .map(|i| format!("?{}", i))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let args: Vec<Box<dyn rusqlite::ToSql>> = ids
+        .iter()
+        .map(|id| Box::new(id.clone()) as Box<dyn rusqlite::ToSql>)
+        .collect();
+
+    // 先收集待删行引用的图片路径，提交后再做文件清理
+    let mut image_paths: Vec<String> = {
+        let mut stmt = tx.prepare(&format!(
+            "SELECT DISTINCT content FROM clipboard_history WHERE content_type = 'image' AND id IN ({})",
+            placeholders
+        ))?;
+        let rows = stmt.query_map(
+            rusqlite::params_from_iter(args.iter().map(|a| a.as_ref())),
+            |row| row.get(0),
+        )?;
+        rows.collect::<Result<Vec<_>, _>>()?
+    };
+
+    let affected = tx.execute(
+        &format!("DELETE FROM clipboard_history WHERE id IN ({})", placeholders),
+        rusqlite::params_from_iter(args.iter().map(|a| a.as_ref())),
+    )?;
+
+    // 只删除删完后不再被任何记录引用的文件
+    image_paths.retain(|path| {
+        tx.query_row(
+            "SELECT COUNT(*) FROM clipboard_history WHERE content = ?1 AND content_type = 'image'",
+            params![path],
+            |row| row.get::<_, i64>(0),
+        )
+        .unwrap_or(0)
+            == 0
+    });
+
+    tx.commit()?;
+
+    // 文件清理放在提交后：数据库结果已生效，文件删不掉只记日志
+    for image_path in image_paths {
+        let path = std::path::Path::new(&image_path);
+        if path.exists() {
+            if let Err(e) = std::fs::remove_file(path) {
+                eprintln!("[Clipboard] Failed to delete image file {}: {}", image_path, e);
+            }
+        }
+        remove_thumbnail_for(&image_path);
+    }
+
+    Ok(affected as u32)
+}
+
+/// 批量设置收藏状态（一个事务内完成），返回实际更新的条数
+pub fn set_favorite_clipboard_items(
+    ids: Vec<String>,
+    favorite: bool,
+    app_data_dir: &PathBuf,
+) -> Result<u32, ClipboardError> {
+    if ids.is_empty() {
+        return Ok(0);
+    }
+
+    let mut conn = db::get_connection(app_data_dir)?;
+    let tx = conn.transaction()?;
+
+    let placeholders = (2..=ids.len() + 1)
+        .map(|i| format!("?{}", i))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let mut args: Vec<Box<dyn rusqlite::ToSql>> =
+        vec![Box::new(if favorite { 1i64 } else { 0i64 })];
+    for id in &ids {
+        args.push(Box::new(id.clone()));
+    }
+
+    let affected = tx.execute(
+        &format!(
+            "UPDATE clipboard_history SET is_favorite = ?1 WHERE id IN ({})",
+            placeholders
+        ),
+        rusqlite::params_from_iter(args.iter().map(|a| a.as_ref())),
+    )?;
+
+    tx.commit()?;
+    Ok(affected as u32)
+}
+
+/// 清空剪切板历史
+pub fn clear_clipboard_history(app_data_dir: &PathBuf) -> Result<(), String> {
+    let conn = db::get_connection(app_data_dir)?;
+    
+    // 先查询所有要删除的图片项（去重）
+    let mut stmt = conn
+        .prepare("SELECT DISTINCT content FROM clipboard_history WHERE is_favorite = 0 AND content_type = 'image'")
+        .map_err(|e| format!("Failed to prepare query: {}", e))?;
+    
+    let image_paths: Vec<String> = stmt
+        .query_map([], |row| row.get(0))
+        .map_err(|e| format!("Failed to query image paths: {}", e))?
+        .filter_map(|r| r.ok())
+        .collect();
+    
+    // 释放语句，避免持有数据库连接
+    drop(stmt);
+    
+    println!("[Clipboard] Found {} unique image files to check", image_paths.len());
+    
+    // 先删除数据库记录
+    conn.execute("DELETE FROM clipboard_history WHERE is_favorite = 0", [])
+        .map_err(|e| format!("Failed to clear clipboard history: {}", e))?;
+    
+    // 然后检查并删除图片文件
+    let mut deleted_count = 0;
+    for image_path in image_paths {
+        // 检查是否还有收藏记录引用这个图片
+        let ref_count: i64 = conn
+            .query_row(
+                "SELECT COUNT(*) FROM clipboard_history WHERE content = ?1 AND content_type = 'image'",
+                params![image_path],
+                |row| row.get(0),
+            )
+            .unwrap_or(0);
+        
+        if ref_count > 0 {
+            println!("[Clipboard] Skipping {} (still referenced by {} favorite(s))", image_path, ref_count);
+            continue;
+        }
+        
+        let path = std::path::Path::new(&image_path);
+        println!("[Clipboard] Attempting to delete: {}", image_path);
+        
+        if path.exists() {
+            // 重试删除最多3次
+            let mut retry = 0;
+            let max_retries = 3;
+            let mut success = false;
+            
+            while retry < max_retries {
+                match std::fs::remove_file(path) {
+                    Ok(_) => {
+                        deleted_count += 1;
+                        println!("[Clipboard] Successfully deleted: {}", image_path);
+                        success = true;
+                        break;
+                    }
+                    Err(e) => {
+                        retry += 1;
+                        eprintln!("[Clipboard] Failed to delete image file {} (attempt {}): {}", image_path, retry, e);
+                        if retry < max_retries {
+                            std::thread::sleep(std::time::Duration::from_millis(100));
+                        }
+                    }
+                }
+            }
+            
+            if !success {
+                eprintln!("[Clipboard] Gave up deleting {} after {} attempts", image_path, max_retries);
+            }
+        } else {
+            eprintln!("[Clipboard] Image file not found: {}", image_path);
+        }
+        remove_thumbnail_for(&image_path);
+    }
+
+    println!("[Clipboard] Successfully deleted {} image files", deleted_count);
+    Ok(())
+}
+
+/// 把查询拆成 FTS5 的 MATCH 表达式：按空白分词做前缀匹配，多词取 AND
+fn fts_match_expr(query: &str) -> Option<String> {
+    let terms: Vec<String> = query
+        .split_whitespace()
+        .map(|t| format!("\"{}\"*", t.replace('"', "\"\"")))
+        .collect();
+
+    if terms.is_empty() {
+        None
+    } else {
+        Some(terms.join(" AND "))
+    }
+}
+
+/// 用 FTS 索引搜索，收藏优先、其余按相关度排序
+fn search_with_fts(
+    conn: &rusqlite::Connection,
+    match_expr: &str,
+) -> Result<Vec<ClipboardItem>, String> {
+    // 连接查询里 fts 表也有 content 列，主表列需要加前缀消歧
+    let columns = ITEM_COLUMNS
+        .split(", ")
+        .map(|c| format!("h.{}", c))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {}
+             FROM clipboard_history h
+             JOIN clipboard_fts f ON f.rowid = h.rowid
+             WHERE clipboard_fts MATCH ?1
+             ORDER BY h.is_favorite DESC, f.rank",
+            columns
+        ))
+        .map_err(|e| format!("Failed to prepare FTS search: {}", e))?;
+
+    let rows = stmt
+        .query_map(params![match_expr], map_item_row)
+        .map_err(|e| format!("Failed to run FTS search: {}", e))?;
+
+    let mut items = Vec::new();
+    for row in rows {
+        items.push(row.map_err(|e| format!("Failed to read clipboard row: {}", e))?);
+    }
+    Ok(items)
+}
+
+/// 解锁状态下补充搜索加密条目：LIKE/FTS 只见密文，这里解密后按子串匹配；
+/// 锁定时静默跳过，加密条目不出现在搜索结果里
+fn merge_encrypted_matches(
+    conn: &rusqlite::Connection,
+    query: &str,
+    items: &mut Vec<ClipboardItem>,
+) {
+    let key = match current_encryption_key() {
+        Some(key) => key,
+        None => return,
+    };
+
+    let mut stmt = match conn.prepare(&format!(
+        "SELECT {} FROM clipboard_history WHERE content LIKE '{}%' ORDER BY is_favorite DESC, created_at DESC",
+        ITEM_COLUMNS, ENCRYPTED_PREFIX
+    )) {
+        Ok(stmt) => stmt,
+        Err(_) => return,
+    };
+    let rows = match stmt.query_map([], map_item_row) {
+        Ok(rows) => rows,
+        Err(_) => return,
+    };
+
+    let existing: std::collections::HashSet<String> =
+        items.iter().map(|i| i.id.clone()).collect();
+    let needle = query.to_lowercase();
+    for item in rows.flatten() {
+        if existing.contains(&item.id) {
+            continue;
+        }
+        if let Ok(plain) = decrypt_content(&key, &item.content) {
+            if plain.to_lowercase().contains(&needle) {
+                items.push(item);
+            }
+        }
+    }
+}
+
+/// 按来源应用过滤（支持基名或完整路径匹配），None 表示不过滤
+fn retain_by_source(items: &mut Vec<ClipboardItem>, source_app: Option<&str>) {
+    if let Some(src) = source_app {
+        items.retain(|item| {
+            item.source_app
+                .as_deref()
+                .map(|stored| source_matches(stored, src))
+                .unwrap_or(false)
+        });
+    }
+}
+
+/// 按文本子类型过滤，None 表示不过滤；老数据缺 kind 时现场分类再比较
+fn retain_by_kind(items: &mut Vec<ClipboardItem>, kind: Option<&str>) {
+    if let Some(kind) = kind {
+        items.retain(|item| match &item.kind {
+            Some(k) => k == kind,
+            None => item.content_type == "text" && detect_text_kind(&item.content) == kind,
+        });
+    }
+}
+
+/// 搜索剪切板历史：优先走 FTS5 全文索引（多词 AND、按相关度排序），
+/// 索引不可用的旧库退回 LIKE 扫描；可选按来源应用过滤
+pub fn search_clipboard_items(
+    query: &str,
+    source_app: Option<&str>,
+    kind: Option<&str>,
+    app_data_dir: &PathBuf,
+) -> Result<Vec<ClipboardItem>, String> {
+    let conn = db::get_readonly_connection(app_data_dir)?;
+
+    let normalized = normalize_query(query, app_data_dir);
+
+    if fts_exists(&conn) {
+        if let Some(match_expr) = fts_match_expr(&normalized) {
+            match search_with_fts(&conn, &match_expr) {
+                Ok(mut items) => {
+                    merge_encrypted_matches(&conn, &normalized, &mut items);
+                    retain_by_source(&mut items, source_app);
+                    retain_by_kind(&mut items, kind);
+                    apply_previews(&mut items, app_data_dir);
+                    return Ok(items);
+                }
+                // 语法或分词器问题不致命，退回 LIKE 扫描
+                Err(e) => eprintln!("[Clipboard] FTS search failed, falling back to LIKE: {}", e),
+            }
+        }
+    }
+
+    let like = format!("%{}%", normalized.to_lowercase());
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {}
+             FROM clipboard_history
+             WHERE lower(content) LIKE ?1
+             ORDER BY is_favorite DESC, created_at DESC",
+            ITEM_COLUMNS
+        ))
+        .map_err(|e| format!("Failed to prepare clipboard search: {}", e))?;
+
+    let rows = stmt
+        .query_map(params![like], map_item_row)
+        .map_err(|e| format!("Failed to iterate clipboard search: {}", e))?;
+
+    let mut items = Vec::new();
+    for row in rows {
+        items.push(row.map_err(|e| format!("Failed to read clipboard row: {}", e))?);
+    }
+    drop(stmt);
+    merge_encrypted_matches(&conn, &normalized, &mut items);
+    retain_by_source(&mut items, source_app);
+    retain_by_kind(&mut items, kind);
+    apply_previews(&mut items, app_data_dir);
+    Ok(items)
+}
+
+/// 文本文件导入结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportReport {
+    pub imported: u32,
+    pub skipped: u32,
+}
+
+/// 将目录下的文本文件批量导入为剪切板项，文件名写入备注
+/// 跳过二进制/非 UTF-8 文件，返回导入和跳过的数量
+pub fn import_text_files(
+    dir: &str,
+    app_data_dir: &PathBuf,
+    recursive: bool,
+) -> Result<ImportReport, String> {
+    let dir_path = std::path::Path::new(dir);
+    if !dir_path.is_dir() {
+        return Err(format!("Not a directory: {}", dir));
+    }
+
+    let mut report = ImportReport {
+        imported: 0,
+        skipped: 0,
+    };
+    import_text_files_in_dir(dir_path, app_data_dir, recursive, &mut report)?;
+    Ok(report)
+}
+
+fn import_text_files_in_dir(
+    dir: &std::path::Path,
+    app_data_dir: &PathBuf,
+    recursive: bool,
+    report: &mut ImportReport,
+) -> Result<(), String> {
+    let entries = std::fs::read_dir(dir)
+        .map_err(|e| format!("Failed to read directory {}: {}", dir.display(), e))?;
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+
+        if path.is_dir() {
+            if recursive {
+                import_text_files_in_dir(&path, app_data_dir, recursive, report)?;
+            }
+            continue;
+        }
+
+        // 只导入能按 UTF-8 读取且不含 NUL 的文本文件，其余视为二进制跳过
+        let bytes = match std::fs::read(&path) {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                eprintln!("[Clipboard] Failed to read {}: {}", path.display(), e);
+                report.skipped += 1;
+                continue;
+            }
+        };
+
+        if bytes.contains(&0) {
+            report.skipped += 1;
+            continue;
+        }
+
+        let content = match String::from_utf8(bytes) {
+            Ok(content) => content,
+            Err(_) => {
+                report.skipped += 1;
+                continue;
+            }
+        };
+
+        if content.trim().is_empty() {
+            report.skipped += 1;
+            continue;
+        }
+
+        let file_name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        match add_clipboard_item_impl(content, "text".to_string(), app_data_dir, false, None) {
+            // 去重命中的是历史里已有的条目：不覆盖它的备注，计为跳过
+            Ok((_, true)) => {
+                report.skipped += 1;
+            }
+            Ok((item, false)) => {
+                // 用文件名做备注，方便在历史里找到来源
+                let conn = db::get_connection(app_data_dir)?;
+                conn.execute(
+                    "UPDATE clipboard_history SET note = ?1 WHERE id = ?2",
+                    params![file_name, item.id],
+                )
+                .map_err(|e| format!("Failed to set note for imported item: {}", e))?;
+                report.imported += 1;
+            }
+            Err(e) => {
+                eprintln!("[Clipboard] Failed to import {}: {}", path.display(), e);
+                report.skipped += 1;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// 导出过滤条件，所有字段可选，不填表示不过滤
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportFilter {
+    #[serde(default)]
+    pub query: Option<String>,
+    /// 起始时间（含，Unix 秒）
+    #[serde(default)]
+    pub date_from: Option<u64>,
+    /// 结束时间（含，Unix 秒）
+    #[serde(default)]
+    pub date_to: Option<u64>,
+    #[serde(default)]
+    pub content_type: Option<String>,
+    #[serde(default)]
+    pub favorites_only: bool,
+}
+
+/// 按条件导出剪切板历史为 JSON 文件，返回导出的条数
+pub fn export_filtered(
+    app_data_dir: &PathBuf,
+    out_path: &str,
+    filter: &ExportFilter,
+) -> Result<u32, String> {
+    let conn = db::get_readonly_connection(app_data_dir)?;
+
+    let mut sql = format!("SELECT {} FROM clipboard_history", ITEM_COLUMNS);
+    let mut clauses: Vec<String> = Vec::new();
+    let mut args: Vec<Box<dyn rusqlite::ToSql>> = Vec::new();
+
+    // 开启加密后 content 列是密文，LIKE 谓词改到解密后的明文上执行
+    let encryption_on = clipboard_encryption_enabled(&conn);
+    if let Some(query) = filter.query.as_deref() {
+        if !encryption_on {
+            clauses.push(format!("lower(content) LIKE ?{}", args.len() + 1));
+            args.push(Box::new(format!("%{}%", query.to_lowercase())));
+        }
+    }
+    if let Some(from) = filter.date_from {
+        clauses.push(format!("created_at >= ?{}", args.len() + 1));
+        args.push(Box::new(from as i64));
+    }
+    if let Some(to) = filter.date_to {
+        clauses.push(format!("created_at <= ?{}", args.len() + 1));
+        args.push(Box::new(to as i64));
+    }
+    if let Some(content_type) = filter.content_type.as_deref() {
+        clauses.push(format!("content_type = ?{}", args.len() + 1));
+        args.push(Box::new(content_type.to_string()));
+    }
+    if filter.favorites_only {
+        clauses.push("is_favorite = 1".to_string());
+    }
+
+    if !clauses.is_empty() {
+        sql.push_str(" WHERE ");
+        sql.push_str(&clauses.join(" AND "));
+    }
+    sql.push_str(" ORDER BY created_at DESC");
+
+    let mut stmt = conn
+        .prepare(&sql)
+        .map_err(|e| format!("Failed to prepare export query: {}", e))?;
+
+    let rows = stmt
+        .query_map(
+            rusqlite::params_from_iter(args.iter().map(|a| a.as_ref())),
+            map_item_row,
+        )
+        .map_err(|e| format!("Failed to iterate export items: {}", e))?;
+
+    let mut items = Vec::new();
+    for row in rows {
+        items.push(row.map_err(|e| format!("Failed to read clipboard row: {}", e))?);
+    }
+
+    // 导出文件里要的是明文；锁定时报错而不是导出一堆密文
+    for item in items.iter_mut() {
+        decrypt_for_output(item)?;
+    }
+    if encryption_on {
+        if let Some(query) = filter.query.as_deref() {
+            let needle = query.to_lowercase();
+            items.retain(|item| item.content.to_lowercase().contains(&needle));
+        }
+    }
+
+    let json = serde_json::to_string_pretty(&items)
+        .map_err(|e| format!("Failed to serialize export: {}", e))?;
+    std::fs::write(out_path, json)
+        .map_err(|e| format!("Failed to write export file {}: {}", out_path, e))?;
+
+    println!("[Clipboard] Exported {} items to {}", items.len(), out_path);
+
+    Ok(items.len() as u32)
+}
+
+/// 跨类型去重结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CollapseReport {
+    /// 合并的内容组数
+    pub groups_collapsed: u32,
+    /// 被删除的重复项数
+    pub items_removed: u32,
+}
+
+/// 条目的具体程度：content_type 里自动检测出的只有 "table"，
+/// 文本子类型（url/email/hex_color/code）记录在 kind 列，
+/// 老数据缺 kind 时现场分类再比较
+fn item_specificity(item: &ClipboardItem) -> u32 {
+    match item.content_type.as_str() {
+        "table" => 2,
+        "text" => {
+            let kind = item
+                .kind
+                .clone()
+                .unwrap_or_else(|| detect_text_kind(&item.content).to_string());
+            if kind == "plain" {
+                0
+            } else {
+                2
+            }
+        }
+        _ => 1,
+    }
+}
+
+/// 合并一组重复项的标签（去重、保持出现顺序），空结果返回 None
+fn merge_tags(group: &[ClipboardItem]) -> Option<String> {
+    let mut merged: Vec<String> = Vec::new();
+    for item in group {
+        for tag in &item.tags {
+            if !merged.contains(tag) {
+                merged.push(tag.clone());
+            }
+        }
+    }
+    if merged.is_empty() {
+        None
+    } else {
+        serde_json::to_string(&merged).ok()
+    }
+}
+
+/// 合并内容完全相同但类型不同的重复项：保留类型最具体的一条，
+/// 收藏状态和备注合并到保留项上。主要用于开启类型自动检测后清理旧历史。
+pub fn collapse_cross_type_duplicates(app_data_dir: &PathBuf) -> Result<CollapseReport, String> {
+    let conn = db::get_connection(app_data_dir)?;
+
+    // 找出存在多种类型的相同内容
+    let mut stmt = conn
+        .prepare(
+            "SELECT content FROM clipboard_history
+             GROUP BY content
+             HAVING COUNT(DISTINCT content_type) > 1",
+        )
+        .map_err(|e| format!("Failed to prepare duplicate query: {}", e))?;
+
+    let contents: Vec<String> = stmt
+        .query_map([], |row| row.get(0))
+        .map_err(|e| format!("Failed to query duplicate contents: {}", e))?
+        .filter_map(|r| r.ok())
+        .collect();
+
+    drop(stmt);
+
+    let mut report = CollapseReport {
+        groups_collapsed: 0,
+        items_removed: 0,
+    };
+
+    for content in contents {
+        let mut stmt = conn
+            .prepare(&format!(
+                "SELECT {} FROM clipboard_history WHERE content = ?1",
+                ITEM_COLUMNS
+            ))
+            .map_err(|e| format!("Failed to prepare group query: {}", e))?;
+
+        let mut group: Vec<ClipboardItem> = stmt
+            .query_map(params![content], map_item_row)
+            .map_err(|e| format!("Failed to query duplicate group: {}", e))?
+            .filter_map(|r| r.ok())
+            .collect();
+
+        drop(stmt);
+
+        if group.len() < 2 {
+            continue;
+        }
+
+        // 最具体的条目优先，其次保留较新的一条
+        group.sort_by(|a, b| {
+            item_specificity(b)
+                .cmp(&item_specificity(a))
+                .then(b.created_at.cmp(&a.created_at))
+        });
+
+        let survivor = &group[0];
+        let merged_favorite = group.iter().any(|item| item.is_favorite);
+        let merged_note = survivor
+            .note
+            .clone()
+            .or_else(|| group.iter().find_map(|item| item.note.clone()));
+        let merged_tags = merge_tags(&group);
+
+        conn.execute(
+            "UPDATE clipboard_history SET is_favorite = ?1, note = ?2, tags = ?3 WHERE id = ?4",
+            params![
+                if merged_favorite { 1 } else { 0 },
+                merged_note,
+                merged_tags,
+                survivor.id
+            ],
+        )
+        .map_err(|e| format!("Failed to update surviving item: {}", e))?;
+
+        for item in &group[1..] {
+            conn.execute(
+                "DELETE FROM clipboard_history WHERE id = ?1",
+                params![item.id],
+            )
+            .map_err(|e| format!("Failed to delete duplicate item {}: {}", item.id, e))?;
+            report.items_removed += 1;
+        }
+
+        report.groups_collapsed += 1;
+    }
+
+    println!(
+        "[Clipboard] Collapsed {} duplicate groups ({} items removed)",
+        report.groups_collapsed, report.items_removed
+    );
+
+    Ok(report)
+}
+
+/// 设置条目的标签列表（覆盖式），返回更新后的条目
+pub fn set_item_tags(
+    id: String,
+    tags: Vec<String>,
+    app_data_dir: &PathBuf,
+) -> Result<ClipboardItem, String> {
+    let conn = db::get_connection(app_data_dir)?;
+
+    let tags_json = if tags.is_empty() {
+        None
+    } else {
+        Some(
+            serde_json::to_string(&tags)
+                .map_err(|e| format!("Failed to serialize tags: {}", e))?,
+        )
+    };
+
+    let affected = conn
+        .execute(
+            "UPDATE clipboard_history SET tags = ?1 WHERE id = ?2",
+            params![tags_json, id],
+        )
+        .map_err(|e| format!("Failed to update tags: {}", e))?;
+    if affected == 0 {
+        return Err(format!("Clipboard item {} not found", id));
+    }
+
+    conn.query_row(
+        &format!(
+            "SELECT {} FROM clipboard_history WHERE id = ?1",
+            ITEM_COLUMNS
+        ),
+        params![id],
+        map_item_row,
+    )
+    .map_err(|e| format!("Failed to load clipboard item: {}", e))
+}
+
+/// 聚合全部标签及其条目数，按数量降序（标签存为 JSON，在 Rust 侧聚合）
+pub fn list_tags(app_data_dir: &PathBuf) -> Result<Vec<(String, u64)>, String> {
+    let conn = db::get_readonly_connection(app_data_dir)?;
+
+    let mut stmt = conn
+        .prepare("SELECT tags FROM clipboard_history WHERE tags IS NOT NULL")
+        .map_err(|e| format!("Failed to prepare tags query: {}", e))?;
+
+    let rows = stmt
+        .query_map([], |row| row.get::<_, String>(0))
+        .map_err(|e| format!("Failed to iterate tags: {}", e))?;
+
+    let mut counts: std::collections::HashMap<String, u64> = std::collections::HashMap::new();
+    for row in rows {
+        let json = row.map_err(|e| format!("Failed to read tags row: {}", e))?;
+        let tags: Vec<String> = serde_json::from_str(&json).unwrap_or_default();
+        for tag in tags {
+            *counts.entry(tag).or_insert(0) += 1;
+        }
+    }
+
+    let mut result: Vec<(String, u64)> = counts.into_iter().collect();
+    // 数量相同按名称排序，保证结果稳定
+    result.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    Ok(result)
+}
+
+/// 将一次复制的文件路径列表入库
+/// "single" 模式合成一条 "file" 项（路径按行拼接）；
+/// "per_file" 模式每个路径一条，共享 group_id，便于 UI 按组展示
+pub fn add_clipboard_file_paths(
+    paths: Vec<String>,
+    app_data_dir: &PathBuf,
+) -> Result<Vec<ClipboardItem>, String> {
+    if paths.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let settings = settings::load_settings(app_data_dir).unwrap_or_default();
+    if settings.clipboard_file_capture_mode != "per_file" || paths.len() == 1 {
+        let item = add_clipboard_item(paths.join("\n"), "file".to_string(), app_data_dir)?;
+        return Ok(vec![item]);
+    }
+
+    let group_id = new_item_id("filegroup");
+    let conn = db::get_connection(app_data_dir)?;
+
+    let mut items = Vec::new();
+    for path in paths {
+        let mut item = add_clipboard_item(path, "file".to_string(), app_data_dir)?;
+        conn.execute(
+            "UPDATE clipboard_history SET group_id = ?1 WHERE id = ?2",
+            params![group_id, item.id],
+        )
+        .map_err(|e| format!("Failed to set group id: {}", e))?;
+        item.group_id = Some(group_id.clone());
+        items.push(item);
+    }
+
+    Ok(items)
+}
+
+/// 判断存量 source_app 是否匹配查询：完整路径、文件名或不带扩展名的基名均可。
+/// Windows 监控存的是反斜杠路径，这里按两种分隔符取基名，各平台行为一致
+pub fn source_matches(stored: &str, query: &str) -> bool {
+    if stored.eq_ignore_ascii_case(query) {
+        return true;
+    }
+
+    let file_name = stored
+        .rsplit(['\\', '/'])
+        .next()
+        .unwrap_or(stored)
+        .to_lowercase();
+    let stem = file_name
+        .rsplit_once('.')
+        .map(|(stem, _)| stem)
+        .filter(|s| !s.is_empty())
+        .unwrap_or(file_name.as_str());
+    let query = query.to_lowercase();
+
+    file_name == query || stem == query
+}
+
+/// 永久保存的代码片段/常用内容，独立于滚动历史，不参与自动淘汰
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Snippet {
+    pub id: String,
+    pub label: String,
+    pub content: String,
+    pub content_type: String,
+    pub created_at: u64,
+}
+
+/// 把历史条目提升为永久片段（复制内容，原条目不受影响）
+pub fn save_as_snippet(
+    id: &str,
+    label: &str,
+    app_data_dir: &PathBuf,
+) -> Result<Snippet, String> {
+    let conn = db::get_connection(app_data_dir)?;
+
+    let source: Option<(String, String)> = conn
+        .query_row(
+            "SELECT content, content_type FROM clipboard_history WHERE id = ?1",
+            params![id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()
+        .map_err(|e| format!("Failed to load clipboard item: {}", e))?;
+
+    let (content, content_type) =
+        source.ok_or_else(|| format!("Clipboard item {} not found", id))?;
+
+    // 片段表不参与加密开关的改写，必须存明文；锁定时报错而不是存密文
+    let content = if is_encrypted_content(&content) {
+        let key = current_encryption_key()
+            .ok_or_else(|| "Clipboard encryption is locked".to_string())?;
+        decrypt_content(&key, &content)?
+    } else {
+        content
+    };
+
+    let snippet = Snippet {
+        id: new_item_id("snippet"),
+        label: label.to_string(),
+        content,
+        content_type,
+        created_at: now_ts(),
+    };
+
+    conn.execute(
+        "INSERT INTO snippets (id, label, content, content_type, created_at)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![
+            snippet.id,
+            snippet.label,
+            snippet.content,
+            snippet.content_type,
+            snippet.created_at as i64
+        ],
+    )
+    .map_err(|e| format!("Failed to save snippet: {}", e))?;
+
+    Ok(snippet)
+}
+
+/// 获取全部片段（按保存时间倒序）
+pub fn get_snippets(app_data_dir: &PathBuf) -> Result<Vec<Snippet>, String> {
+    let conn = db::get_readonly_connection(app_data_dir)?;
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, label, content, content_type, created_at
+             FROM snippets ORDER BY created_at DESC",
+        )
+        .map_err(|e| format!("Failed to prepare snippet query: {}", e))?;
+
+    let snippets = stmt
+        .query_map([], |row| {
+            Ok(Snippet {
+                id: row.get(0)?,
+                label: row.get(1)?,
+                content: row.get(2)?,
+                content_type: row.get(3)?,
+                created_at: row.get::<_, i64>(4)? as u64,
+            })
+        })
+        .map_err(|e| format!("Failed to query snippets: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to read snippets: {}", e))?;
+
+    Ok(snippets)
+}
+
+/// 删除片段
+pub fn delete_snippet(id: &str, app_data_dir: &PathBuf) -> Result<(), String> {
+    let conn = db::get_connection(app_data_dir)?;
+    conn.execute("DELETE FROM snippets WHERE id = ?1", params![id])
+        .map_err(|e| format!("Failed to delete snippet: {}", e))?;
+    Ok(())
+}
+
+/// 启动收藏自动备份线程：配置了备份路径时定期把全部收藏导出为 JSON，
+/// 先写临时文件再原子改名覆盖；收藏没变化时跳过写盘
+pub fn start_favorite_backup(app_data_dir: PathBuf) -> Result<(), String> {
+    std::thread::spawn(move || {
+        let mut last_hash = String::new();
+
+        loop {
+            std::thread::sleep(std::time::Duration::from_secs(300));
+
+            let path = match settings::load_settings(&app_data_dir) {
+                Ok(s) => match s.clipboard_favorite_backup_path {
+                    Some(path) if !path.is_empty() => path,
+                    _ => continue,
+                },
+                Err(_) => continue,
+            };
+
+            match backup_favorites(&app_data_dir, &path, &last_hash) {
+                Ok(Some(hash)) => last_hash = hash,
+                Ok(None) => {}
+                Err(e) => monitor_log(
+                    LogLevel::Error,
+                    "backup",
+                    None,
+                    &format!("Failed to back up favorites: {}", e),
+                ),
+            }
+        }
+    });
+
+    Ok(())
+}
+
+/// 收藏有变化时备份到目标文件，返回新内容的哈希；无变化返回 None
+fn backup_favorites(
+    app_data_dir: &PathBuf,
+    path: &str,
+    last_hash: &str,
+) -> Result<Option<String>, String> {
+    let conn = db::get_readonly_connection(app_data_dir)?;
+
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM clipboard_history WHERE is_favorite = 1 ORDER BY created_at DESC",
+            ITEM_COLUMNS
+        ))
+        .map_err(|e| format!("Failed to prepare favorites query: {}", e))?;
+
+    let favorites: Vec<ClipboardItem> = stmt
+        .query_map([], map_item_row)
+        .map_err(|e| format!("Failed to query favorites: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to read favorites: {}", e))?;
+
+    let json = serde_json::to_string_pretty(&favorites)
+        .map_err(|e| format!("Failed to serialize favorites: {}", e))?;
+
+    let hash = content_hash(&json);
+    if hash == last_hash {
+        return Ok(None);
+    }
+
+    // 先写临时文件再改名，避免备份文件出现半截内容
+    let tmp_path = format!("{}.tmp", path);
+    std::fs::write(&tmp_path, &json)
+        .map_err(|e| format!("Failed to write backup file: {}", e))?;
+    std::fs::rename(&tmp_path, path)
+        .map_err(|e| format!("Failed to replace backup file: {}", e))?;
+
+    Ok(Some(hash))
+}
+
+/// 剪切板历史的导出归档：全部条目加上引用图片文件的 base64 内容
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ClipboardArchive {
+    /// 归档格式版本
+    pub version: u32,
+    /// 全部历史条目（按时间升序）
+    pub items: Vec<ClipboardItem>,
+    /// 图片文件名 -> base64 编码的文件内容
+    #[serde(default)]
+    pub images: std::collections::HashMap<String, String>,
+}
+
+/// 导出全部历史到 JSON 归档文件，返回导出的条目数。
+/// 图片项的文件内容以 base64 内嵌，换机导入后可完整还原
+pub fn export_clipboard_history(
+    app_data_dir: &PathBuf,
+    out_path: &std::path::Path,
+) -> Result<u32, String> {
+    let conn = db::get_readonly_connection(app_data_dir)?;
+
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM clipboard_history ORDER BY created_at ASC",
+            ITEM_COLUMNS
+        ))
+        .map_err(|e| format!("Failed to prepare export query: {}", e))?;
+
+    let mut items: Vec<ClipboardItem> = stmt
+        .query_map([], map_item_row)
+        .map_err(|e| format!("Failed to query clipboard items: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to read clipboard items: {}", e))?;
+
+    // 归档不带密钥参数，必须以明文导出才能在目标机器还原；锁定时报错
+    for item in items.iter_mut() {
+        decrypt_for_output(item)?;
+    }
+
+    use base64::Engine;
+    let mut images = std::collections::HashMap::new();
+    for item in &items {
+        if item.content_type != "image" {
+            continue;
+        }
+        let path = std::path::Path::new(&item.content);
+        let file_name = match path.file_name().and_then(|n| n.to_str()) {
+            Some(name) => name.to_string(),
+            None => continue,
+        };
+        if images.contains_key(&file_name) {
+            continue;
+        }
+        match std::fs::read(path) {
+            Ok(bytes) => {
+                images.insert(
+                    file_name,
+                    base64::engine::general_purpose::STANDARD.encode(&bytes),
+                );
+            }
+            // 文件缺失时仍导出条目本身，导入端按缺图处理
+            Err(e) => eprintln!("[Clipboard] Failed to read image for export {}: {}", item.content, e),
+        }
+    }
+
+    let count = items.len() as u32;
+    let archive = ClipboardArchive {
+        version: 1,
+        items,
+        images,
+    };
+
+    let json = serde_json::to_string_pretty(&archive)
+        .map_err(|e| format!("Failed to serialize archive: {}", e))?;
+
+    // 先写临时文件再改名，避免导出文件出现半截内容
+    let tmp_path = out_path.with_extension("tmp");
+    std::fs::write(&tmp_path, &json)
+        .map_err(|e| format!("Failed to write archive file: {}", e))?;
+    std::fs::rename(&tmp_path, out_path)
+        .map_err(|e| format!("Failed to replace archive file: {}", e))?;
+
+    Ok(count)
+}
+
+/// 从 JSON 归档导入历史，返回实际导入的条目数。
+/// merge 为 true 时跳过内容重复的条目，为 false 时先清空再导入；
+/// 图片内容解码到本机的 clipboard_images/ 目录并改写条目路径
+pub fn import_clipboard_history(
+    app_data_dir: &PathBuf,
+    in_path: &std::path::Path,
+    merge: bool,
+) -> Result<u32, String> {
+    let json = std::fs::read_to_string(in_path)
+        .map_err(|e| format!("Failed to read archive file: {}", e))?;
+    let archive: ClipboardArchive = serde_json::from_str(&json)
+        .map_err(|e| format!("Failed to parse archive file: {}", e))?;
+
+    if !merge {
+        clear_all_clipboard_history(app_data_dir)?;
+    }
+
+    let image_dir = app_data_dir.join("clipboard_images");
+    std::fs::create_dir_all(&image_dir)
+        .map_err(|e| format!("Failed to create image directory: {}", e))?;
+
+    use base64::Engine;
+    let conn = db::get_connection(app_data_dir)?;
+    let mut imported = 0u32;
+
+    for mut item in archive.items {
+        // 图片内容先落盘到本机目录，条目路径改写为新位置
+        if item.content_type == "image" {
+            let file_name = match std::path::Path::new(&item.content)
+                .file_name()
+                .and_then(|n| n.to_str())
+            {
+                Some(name) => name.to_string(),
+                None => continue,
+            };
+            let new_path = image_dir.join(&file_name);
+            if let Some(encoded) = archive.images.get(&file_name) {
+                let bytes = base64::engine::general_purpose::STANDARD
+                    .decode(encoded)
+                    .map_err(|e| format!("Failed to decode archived image: {}", e))?;
+                if !new_path.exists() {
+                    std::fs::write(&new_path, &bytes)
+                        .map_err(|e| format!("Failed to write imported image: {}", e))?;
+                }
+            } else if !new_path.exists() {
+                // 归档里没有这张图的内容，本机也没有同名文件，只能跳过
+                eprintln!("[Clipboard] Skipping image item without archived bytes: {}", item.content);
+                continue;
+            }
+            item.content = new_path.to_string_lossy().to_string();
+            item.thumbnail_path = match generate_thumbnail(&item.content) {
+                Ok(thumb) => thumb,
+                Err(e) => {
+                    eprintln!("[Clipboard] Failed to generate thumbnail: {}", e);
+                    None
+                }
+            };
+        }
+
+        if merge {
+            let exists: i64 = conn
+                .query_row(
+                    "SELECT COUNT(*) FROM clipboard_history WHERE content = ?1 AND content_type = ?2",
+                    params![item.content, item.content_type],
+                    |row| row.get(0),
+                )
+                .unwrap_or(0);
+            if exists > 0 {
+                continue;
+            }
+        }
+
+        let hash = if item.content_type == "image" {
+            image_file_hash(&item.content).unwrap_or_else(|| content_hash(&item.content))
+        } else {
+            content_hash(&item.content)
+        };
+        let tags_json = serde_json::to_string(&item.tags)
+            .map_err(|e| format!("Failed to serialize tags: {}", e))?;
+
+        let affected = conn
+            .execute(
+                "INSERT OR IGNORE INTO clipboard_history (id, content, content_type, created_at, is_favorite, raw_content, source_app, note, dominant_color, table_rows, table_cols, group_id, title, tags, mime_type, image_bytes, profile, thumbnail_path, content_hash, truncated, kind, rich_content, rich_format)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20, ?21, ?22, ?23)",
+                params![
+                    item.id,
+                    item.content,
+                    item.content_type,
+                    item.created_at as i64,
+                    item.is_favorite,
+                    item.raw_content,
+                    item.source_app,
+                    item.note,
+                    item.dominant_color,
+                    item.table_rows,
+                    item.table_cols,
+                    item.group_id,
+                    item.title,
+                    tags_json,
+                    item.mime_type,
+                    item.image_bytes.map(|v| v as i64),
+                    item.profile,
+                    item.thumbnail_path,
+                    hash,
+                    item.truncated,
+                    item.kind,
+                    item.rich_content,
+                    item.rich_format,
+                ],
+            )
+            .map_err(|e| format!("Failed to insert imported item: {}", e))?;
+        imported += affected as u32;
+    }
+
+    Ok(imported)
+}
+
+/// 清空全部历史（包括收藏）及其图片文件，供整体替换导入使用
+fn clear_all_clipboard_history(app_data_dir: &PathBuf) -> Result<(), String> {
+    let conn = db::get_connection(app_data_dir)?;
+
+    let mut stmt = conn
+        .prepare("SELECT DISTINCT content FROM clipboard_history WHERE content_type = 'image'")
+        .map_err(|e| format!("Failed to prepare query: {}", e))?;
+
+    let image_paths: Vec<String> = stmt
+        .query_map([], |row| row.get(0))
+        .map_err(|e| format!("Failed to query image paths: {}", e))?
+        .filter_map(|r| r.ok())
+        .collect();
+
+    drop(stmt);
+
+    conn.execute("DELETE FROM clipboard_history", [])
+        .map_err(|e| format!("Failed to clear clipboard history: {}", e))?;
+
+    for image_path in image_paths {
+        let path = std::path::Path::new(&image_path);
+        if path.exists() {
+            if let Err(e) = std::fs::remove_file(path) {
+                eprintln!("[Clipboard] Failed to delete image file {}: {}", image_path, e);
+            }
+        }
+        remove_thumbnail_for(&image_path);
+    }
+
+    Ok(())
+}
+
+/// 两个时间点之间的历史差异
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct HistoryDiff {
+    /// 快照之后新增的条目
+    pub added: Vec<ClipboardItem>,
+    /// 快照中已不存在的条目 id
+    pub removed: Vec<String>,
+}
+
+/// 当前历史的全部 id（按时间倒序），作为之后做差异对比的快照
+pub fn snapshot_ids(app_data_dir: &PathBuf) -> Result<Vec<String>, String> {
+    let conn = db::get_readonly_connection(app_data_dir)?;
+
+    let mut stmt = conn
+        .prepare("SELECT id FROM clipboard_history ORDER BY created_at DESC")
+        .map_err(|e| format!("Failed to prepare snapshot query: {}", e))?;
+
+    let ids = stmt
+        .query_map([], |row| row.get(0))
+        .map_err(|e| format!("Failed to query snapshot ids: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to read snapshot ids: {}", e))?;
+
+    Ok(ids)
+}
+
+/// 对比当前历史与此前的快照，返回新增条目和已删除的 id
+pub fn diff_since(snapshot: &[String], app_data_dir: &PathBuf) -> Result<HistoryDiff, String> {
+    let current = get_all_clipboard_items(app_data_dir)?;
+
+    let snapshot_set: std::collections::HashSet<&str> =
+        snapshot.iter().map(|s| s.as_str()).collect();
+    let current_set: std::collections::HashSet<&str> =
+        current.iter().map(|i| i.id.as_str()).collect();
+
+    let added = current
+        .iter()
+        .filter(|i| !snapshot_set.contains(i.id.as_str()))
+        .cloned()
+        .collect();
+    let removed = snapshot
+        .iter()
+        .filter(|id| !current_set.contains(id.as_str()))
+        .cloned()
+        .collect();
+
+    Ok(HistoryDiff { added, removed })
+}
+
+/// 稳定内容指纹：把用户配置的正则命中的易变部分抹掉后再哈希，
+/// 让只差时间戳/流水号的内容落到同一个去重键上
+fn fingerprint(content: &str, patterns: &[String]) -> String {
+    let mut masked = content.to_string();
+    for pattern in patterns {
+        // 非法正则跳过，不让一条坏配置废掉整个捕获
+        if let Ok(re) = regex::Regex::new(pattern) {
+            masked = re.replace_all(&masked, "").to_string();
+        }
+    }
+    content_hash(&masked)
+}
+
+/// 从文本中扫描 http(s) 链接，按出现顺序去重
+fn extract_links_from_text(content: &str) -> Vec<String> {
+    let mut links: Vec<String> = Vec::new();
+    let mut rest = content;
+
+    while let Some(pos) = rest.find("http") {
+        let candidate = &rest[pos..];
+        let is_url = candidate.starts_with("http://") || candidate.starts_with("https://");
+        if !is_url {
+            rest = &rest[pos + 4..];
+            continue;
+        }
+
+        let end = candidate
+            .find(|c: char| c.is_whitespace() || c == '"' || c == '\'' || c == '<' || c == '>')
+            .unwrap_or(candidate.len());
+        // 去掉粘在链接尾部的标点
+        let link = candidate[..end].trim_end_matches(['.', ',', ';', ':', '!', '?', ')', ']']);
+
+        if !link.is_empty() && !links.iter().any(|l| l == link) {
+            links.push(link.to_string());
+        }
+        rest = &candidate[end..];
+    }
+
+    links
+}
+
+/// 提取条目内容中包含的 http(s) 链接（整段文本里夹带的也算）
+pub fn extract_links(id: &str, app_data_dir: &PathBuf) -> Result<Vec<String>, String> {
+    let conn = db::get_readonly_connection(app_data_dir)?;
+
+    let content: Option<String> = conn
+        .query_row(
+            "SELECT content FROM clipboard_history WHERE id = ?1",
+            params![id],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| format!("Failed to load clipboard item: {}", e))?;
+
+    let content = content.ok_or_else(|| format!("Clipboard item {} not found", id))?;
+
+    // 加密条目要先解密再扫描，否则在密文里永远找不到链接
+    let content = if is_encrypted_content(&content) {
+        let key = current_encryption_key()
+            .ok_or_else(|| "Clipboard encryption is locked".to_string())?;
+        decrypt_content(&key, &content)?
+    } else {
+        content
+    };
+
+    Ok(extract_links_from_text(&content))
+}
+
+/// 格式在优先级列表中的名次，未列出的格式排在末尾
+pub fn format_rank(priority: &[String], format: &str) -> usize {
+    priority
+        .iter()
+        .position(|f| f.eq_ignore_ascii_case(format))
+        .unwrap_or(priority.len())
+}
+
+/// FTS 索引一致性检查结果
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct FtsConsistency {
+    pub table_rows: u64,
+    pub index_rows: u64,
+    pub consistent: bool,
+}
+
+fn fts_exists(conn: &rusqlite::Connection) -> bool {
+    conn.prepare("SELECT rowid FROM clipboard_fts LIMIT 1").is_ok()
+}
+
+/// 重建 FTS 索引（崩溃或手工改库后索引可能与主表脱节）
+pub fn rebuild_fts_index(app_data_dir: &PathBuf) -> Result<(), String> {
+    let conn = db::get_connection(app_data_dir)?;
+    if !fts_exists(&conn) {
+        return Err("FTS index does not exist".to_string());
+    }
+
+    conn.execute("INSERT INTO clipboard_fts(clipboard_fts) VALUES('rebuild')", [])
+        .map_err(|e| format!("Failed to rebuild FTS index: {}", e))?;
+    Ok(())
+}
+
+/// 对比主表与 FTS 索引的行数，发现脱节后可用 rebuild_fts_index 恢复
+pub fn check_fts_consistency(app_data_dir: &PathBuf) -> Result<FtsConsistency, String> {
+    let conn = db::get_readonly_connection(app_data_dir)?;
+    if !fts_exists(&conn) {
+        return Err("FTS index does not exist".to_string());
+    }
+
+    let table_rows: i64 = conn
+        .query_row("SELECT COUNT(*) FROM clipboard_history", [], |row| {
+            row.get(0)
+        })
+        .map_err(|e| format!("Failed to count clipboard items: {}", e))?;
+
+    let index_rows: i64 = conn
+        .query_row("SELECT COUNT(*) FROM clipboard_fts", [], |row| row.get(0))
+        .map_err(|e| format!("Failed to count FTS rows: {}", e))?;
+
+    Ok(FtsConsistency {
+        table_rows: table_rows as u64,
+        index_rows: index_rows as u64,
+        consistent: table_rows == index_rows,
+    })
+}
+
+/// 应用启动时间，未手动重置会话时作为会话起点
+static SESSION_START: std::sync::OnceLock<u64> = std::sync::OnceLock::new();
+
+/// 记录启动时间（应用初始化时调用一次）
+pub fn init_session() {
+    let _ = SESSION_START.set(now_ts());
+}
+
+/// 把会话起点重置为当前时间（"开始新的工作会话"），返回新起点
+/// 手动重置会持久化，应用重启后仍然生效
+pub fn reset_session(app_data_dir: &PathBuf) -> Result<u64, String> {
+    let now = now_ts();
+    let conn = db::get_connection(app_data_dir)?;
+    conn.execute(
+        "INSERT INTO settings (key, value) VALUES ('clipboard_session_start', ?1)
+         ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+        params![now.to_string()],
+    )
+    .map_err(|e| format!("Failed to save session start: {}", e))?;
+    Ok(now)
+}
+
+/// 会话起点：优先用手动重置的持久值，否则用本次启动时间
+fn session_start(app_data_dir: &PathBuf) -> Result<u64, String> {
+    let conn = db::get_readonly_connection(app_data_dir)?;
+    let stored: Option<String> = conn
+        .query_row(
+            "SELECT value FROM settings WHERE key = 'clipboard_session_start' LIMIT 1",
+            [],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| format!("Failed to load session start: {}", e))?;
+
+    let startup = *SESSION_START.get_or_init(now_ts);
+    let manual = stored.and_then(|v| v.parse::<u64>().ok());
+
+    // 手动重置晚于本次启动时以重置为准，否则以启动时间为准
+    Ok(match manual {
+        Some(manual) if manual > startup => manual,
+        _ => startup,
+    })
+}
+
+/// 列出本会话（启动或上次手动重置之后）捕获的条目
+pub fn get_session_items(app_data_dir: &PathBuf) -> Result<Vec<ClipboardItem>, String> {
+    let since = session_start(app_data_dir)?;
+    let conn = db::get_readonly_connection(app_data_dir)?;
+
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM clipboard_history WHERE created_at >= ?1 ORDER BY created_at DESC",
+            ITEM_COLUMNS
+        ))
+        .map_err(|e| format!("Failed to prepare session query: {}", e))?;
+
+    let mut items = stmt
+        .query_map(params![since as i64], map_item_row)
+        .map_err(|e| format!("Failed to query session items: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to read session items: {}", e))?;
+
+    apply_previews(&mut items, app_data_dir);
+    Ok(items)
+}
+
+/// 把一批条目整体移动到目标配置档（一个事务内完成），返回移动的行数
+/// target_profile 为空字符串表示移回默认档
+pub fn move_items_to_profile(
+    ids: Vec<String>,
+    target_profile: &str,
+    app_data_dir: &PathBuf,
+) -> Result<u32, String> {
+    if ids.is_empty() {
+        return Ok(0);
+    }
+
+    let mut conn = db::get_connection(app_data_dir)?;
+    let tx = conn
+        .transaction()
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    let profile: Option<&str> = if target_profile.is_empty() {
+        None
+    } else {
+        Some(target_profile)
+    };
+
+    let mut moved: u32 = 0;
+    for id in &ids {
+        moved += tx
+            .execute(
+                "UPDATE clipboard_history SET profile = ?1 WHERE id = ?2",
+                params![profile, id],
+            )
+            .map_err(|e| format!("Failed to move clipboard item: {}", e))? as u32;
+    }
+
+    tx.commit()
+        .map_err(|e| format!("Failed to commit profile move: {}", e))?;
+
+    Ok(moved)
+}
+
+/// 单日新增数量（day 为 Unix 天序号）
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DayCount {
+    pub day: u64,
+    pub count: u64,
+}
+
+/// 历史增长统计，供设置页做容量预警
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct GrowthStats {
+    pub per_day: Vec<DayCount>,
+    pub avg_per_day: f64,
+    pub current_count: u64,
+    pub max_items: u32,
+    /// 按当前增速推算达到上限的时间戳，已达上限或无增长时为空
+    pub projected_full_at: Option<u64>,
+}
+
+/// 统计最近 window_days 天的每日新增并推算何时触达 clipboard_max_items
+pub fn get_growth_stats(app_data_dir: &PathBuf, window_days: u32) -> Result<GrowthStats, String> {
+    let window_days = window_days.max(1);
+    let conn = db::get_readonly_connection(app_data_dir)?;
+
+    let now = now_ts();
+    let since = now.saturating_sub(window_days as u64 * 86400);
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT created_at / 86400 AS day, COUNT(*) FROM clipboard_history
+             WHERE created_at >= ?1 GROUP BY day ORDER BY day",
+        )
+        .map_err(|e| format!("Failed to prepare growth query: {}", e))?;
+
+    let per_day: Vec<DayCount> = stmt
+        .query_map(params![since as i64], |row| {
+            Ok(DayCount {
+                day: row.get::<_, i64>(0)? as u64,
+                count: row.get::<_, i64>(1)? as u64,
+            })
+        })
+        .map_err(|e| format!("Failed to query growth stats: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to read growth stats: {}", e))?;
+
+    let current_count: i64 = conn
+        .query_row("SELECT COUNT(*) FROM clipboard_history", [], |row| {
+            row.get(0)
+        })
+        .map_err(|e| format!("Failed to count clipboard items: {}", e))?;
+    let current_count = current_count as u64;
+
+    let settings = crate::settings::load_settings(app_data_dir)?;
+    let max_items = settings.clipboard_max_items;
+
+    let total_added: u64 = per_day.iter().map(|d| d.count).sum();
+    let avg_per_day = total_added as f64 / window_days as f64;
+
+    let projected_full_at = if avg_per_day > 0.0 && (max_items as u64) > current_count {
+        let remaining = max_items as u64 - current_count;
+        let days_left = remaining as f64 / avg_per_day;
+        Some(now + (days_left * 86400.0) as u64)
+    } else {
+        None
+    };
+
+    Ok(GrowthStats {
+        per_day,
+        avg_per_day,
+        current_count,
+        max_items,
+        projected_full_at,
+    })
+}
+
+/// 按内容哈希精确查找条目，供外部集成做免明文的去重检查
+/// 顺带补齐历史数据中缺失的 content_hash
+pub fn find_by_content_hash(
+    hash: &str,
+    app_data_dir: &PathBuf,
+) -> Result<Option<ClipboardItem>, String> {
+    let conn = db::get_connection(app_data_dir)?;
+
+    // 旧版本入库的行没有哈希，逐行补齐一次。哈希语义和插入端保持一致：
+    // 图片取文件字节哈希而不是路径哈希；密文行拿不到明文，留空跳过
+    let mut stmt = conn
+        .prepare("SELECT id, content, content_type FROM clipboard_history WHERE content_hash IS NULL")
+        .map_err(|e| format!("Failed to prepare backfill query: {}", e))?;
+    let missing: Vec<(String, String, String)> = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+        .map_err(|e| format!("Failed to query unhashed items: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to read unhashed items: {}", e))?;
+    drop(stmt);
+
+    for (id, content, content_type) in missing {
+        let hash = if content_type == "image" {
+            match image_file_hash(&content) {
+                Some(hash) => hash,
+                None => continue,
+            }
+        } else if is_encrypted_content(&content) {
+            continue;
+        } else {
+            content_hash(&content)
+        };
+        conn.execute(
+            "UPDATE clipboard_history SET content_hash = ?1 WHERE id = ?2",
+            params![hash, id],
+        )
+        .map_err(|e| format!("Failed to backfill content_hash: {}", e))?;
+    }
+
+    conn.query_row(
+        &format!(
+            "SELECT {} FROM clipboard_history WHERE content_hash = ?1 ORDER BY created_at DESC LIMIT 1",
+            ITEM_COLUMNS
+        ),
+        params![hash],
+        map_item_row,
+    )
+    .optional()
+    .map_err(|e| format!("Failed to look up clipboard item by hash: {}", e))
+}
+
+/// 把捕获时解析到的来源应用写进 source_app 列（只补空，不覆盖已有值）
+pub fn record_source_app(
+    id: &str,
+    source_app: &str,
+    app_data_dir: &PathBuf,
+) -> Result<(), String> {
+    let conn = db::get_connection(app_data_dir)?;
+    conn.execute(
+        "UPDATE clipboard_history SET source_app = ?1 WHERE id = ?2 AND source_app IS NULL",
+        params![source_app, id],
+    )
+    .map_err(|e| format!("Failed to record source app: {}", e))?;
+
+    Ok(())
+}
+
+/// 记录条目的富文本变体（只补空值，不覆盖已有内容）
+pub fn record_rich_content(
+    id: &str,
+    rich_content: &str,
+    rich_format: &str,
+    app_data_dir: &PathBuf,
+) -> Result<(), String> {
+    let conn = db::get_connection(app_data_dir)?;
+    conn.execute(
+        "UPDATE clipboard_history SET rich_content = ?1, rich_format = ?2 WHERE id = ?3 AND rich_content IS NULL",
+        params![rich_content, rich_format, id],
+    )
+    .map_err(|e| format!("Failed to record rich content: {}", e))?;
+    Ok(())
+}
+
+/// 若用户开启了来源备注，把 "from <app>" 写进条目的 note 字段
+/// 只在备注为空时写入，不覆盖用户自己填的内容
+pub fn apply_source_note(
+    id: &str,
+    source_app: &str,
+    app_data_dir: &PathBuf,
+) -> Result<(), String> {
+    let settings = crate::settings::load_settings(app_data_dir)?;
+    if !settings.clipboard_note_source_app {
+        return Ok(());
+    }
+
+    let conn = db::get_connection(app_data_dir)?;
+    conn.execute(
+        "UPDATE clipboard_history SET note = ?1 WHERE id = ?2 AND (note IS NULL OR note = '')",
+        params![format!("from {}", source_app), id],
+    )
+    .map_err(|e| format!("Failed to set source note: {}", e))?;
+
+    Ok(())
+}
+
+/// 取指定类型最新的一条（"重新复制上一张图/上一条文本"）
+pub fn latest_of_type(
+    content_type: &str,
+    app_data_dir: &PathBuf,
+) -> Result<Option<ClipboardItem>, String> {
+    let conn = db::get_readonly_connection(app_data_dir)?;
+
+    let mut item: Option<ClipboardItem> = conn
+        .query_row(
+            &format!(
+                "SELECT {} FROM clipboard_history WHERE content_type = ?1 ORDER BY created_at DESC LIMIT 1",
+                ITEM_COLUMNS
+            ),
+            params![content_type],
+            map_item_row,
+        )
+        .optional()
+        .map_err(|e| format!("Failed to load latest clipboard item: {}", e))?;
+
+    // 调用方会把结果回写到系统剪贴板，必须拿到明文
+    if let Some(item) = item.as_mut() {
+        decrypt_for_output(item)?;
+    }
+
+    Ok(item)
+}
+
+/// 将文本写入系统剪贴板
+pub fn set_clipboard_text(text: &str) -> Result<(), String> {
+    #[cfg(target_os = "windows")]
+    {
+        use windows_sys::Win32::Foundation::HWND;
+        use windows_sys::Win32::System::DataExchange::{
+            CloseClipboard, EmptyClipboard, OpenClipboard, SetClipboardData,
+        };
+        use windows_sys::Win32::System::Memory::{
+            GlobalAlloc, GlobalLock, GlobalUnlock, GMEM_MOVEABLE,
+        };
+
+        const CF_UNICODETEXT: u32 = 13;
+
+        // UTF-16 编码并带结尾 NUL
+        let mut wide: Vec<u16> = text.encode_utf16().collect();
+        wide.push(0);
+
+        unsafe {
+            if OpenClipboard(0 as HWND) == 0 {
+                return Err("Failed to open clipboard".to_string());
+            }
+
+            EmptyClipboard();
+
+            let byte_len = wide.len() * std::mem::size_of::<u16>();
+            let h_mem = GlobalAlloc(GMEM_MOVEABLE, byte_len);
+            if h_mem.is_null() {
+                CloseClipboard();
+                return Err("Failed to allocate memory".to_string());
+            }
+
+            let p_mem = GlobalLock(h_mem);
+            if p_mem.is_null() {
+                CloseClipboard();
+                return Err("Failed to lock memory".to_string());
+            }
+
+            std::ptr::copy_nonoverlapping(wide.as_ptr(), p_mem as *mut u16, wide.len());
+            GlobalUnlock(h_mem);
+
+            if SetClipboardData(CF_UNICODETEXT, h_mem as isize) == 0 {
+                CloseClipboard();
+                return Err("Failed to set clipboard data".to_string());
+            }
+
+            CloseClipboard();
+        }
+
+        // 同步监控去重状态，刚写回的文本不会被再次入库
+        set_monitor_dedup_last_text(text);
+        Ok(())
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        use std::io::Write;
+        use std::process::{Command, Stdio};
+
+        let mut child = Command::new("pbcopy")
+            .stdin(Stdio::piped())
+            .spawn()
+            .map_err(|e| format!("Failed to run pbcopy: {}", e))?;
+        if let Some(stdin) = child.stdin.as_mut() {
+            stdin
+                .write_all(text.as_bytes())
+                .map_err(|e| format!("Failed to write clipboard text: {}", e))?;
+        }
+        child
+            .wait()
+            .map_err(|e| format!("Failed to wait for pbcopy: {}", e))?;
+        set_monitor_dedup_last_text(text);
+        Ok(())
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        use std::io::Write;
+        use std::process::{Command, Stdio};
+
+        let mut child = Command::new("xclip")
+            .arg("-selection")
+            .arg("clipboard")
+            .stdin(Stdio::piped())
+            .spawn()
+            .map_err(|e| format!("Failed to run xclip: {}", e))?;
+        if let Some(stdin) = child.stdin.as_mut() {
+            stdin
+                .write_all(text.as_bytes())
+                .map_err(|e| format!("Failed to write clipboard text: {}", e))?;
+        }
+        child
+            .wait()
+            .map_err(|e| format!("Failed to wait for xclip: {}", e))?;
+        set_monitor_dedup_last_text(text);
+        Ok(())
+    }
+
+    #[cfg(not(any(target_os = "windows", target_os = "macos", target_os = "linux")))]
+    {
+        let _ = text;
+        Err("Clipboard write not supported on this platform".to_string())
+    }
+}
+
+/// 按给定顺序取出多个条目，用分隔符拼接后写入系统剪贴板
+/// 文本类条目用内容（优先原始内容保证精确），图片/文件用其路径
+pub fn copy_items_to_clipboard(
+    ids: Vec<String>,
+    separator: &str,
+    app_data_dir: &PathBuf,
+) -> Result<u32, String> {
+    if ids.is_empty() {
+        return Err("No items to copy".to_string());
+    }
+
+    let conn = db::get_readonly_connection(app_data_dir)?;
+
+    let mut parts: Vec<String> = Vec::new();
+    for id in &ids {
+        let item: Option<ClipboardItem> = conn
+            .query_row(
+                &format!(
+                    "SELECT {} FROM clipboard_history WHERE id = ?1",
+                    ITEM_COLUMNS
+                ),
+                params![id],
+                map_item_row,
+            )
+            .optional()
+            .map_err(|e| format!("Failed to load clipboard item: {}", e))?;
+
+        let mut item = item.ok_or_else(|| format!("Clipboard item {} not found", id))?;
+        decrypt_for_output(&mut item)?;
+        // 图片/文件条目的 content 本身就是路径，直接并入
+        let part = item.raw_content.unwrap_or(item.content);
+        parts.push(part);
+    }
+
+    let combined = parts.join(separator);
+    set_clipboard_text(&combined)?;
+
+    Ok(parts.len() as u32)
+}
+
+/// 批量重编码结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReencodeReport {
+    pub converted: u32,
+    pub skipped: u32,
+    pub failed: u32,
+    /// 释放的字节数
+    pub bytes_saved: u64,
+}
+
+/// 把已存储的图片批量重编码为目标格式，更新行的路径/格式/大小并删除旧文件
+/// 目前只内置 PNG 编码器，目标为 "png" 时做最高压缩率的重压缩；
+/// 其他格式返回错误。单个文件失败不会中断整体流程。
+pub fn reencode_images(
+    app_data_dir: &PathBuf,
+    target_format: &str,
+    _quality: u8,
+) -> Result<ReencodeReport, String> {
+    if target_format != "png" {
+        return Err(format!(
+            "Unsupported target format {} (only png encoding is built in)",
+            target_format
+        ));
+    }
+
+    let conn = db::get_connection(app_data_dir)?;
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, content, image_bytes FROM clipboard_history WHERE content_type = 'image'",
+        )
+        .map_err(|e| format!("Failed to prepare image query: {}", e))?;
+
+    let images: Vec<(String, String, Option<i64>)> = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+        .map_err(|e| format!("Failed to query images: {}", e))?
+        .filter_map(|r| r.ok())
+        .collect();
+
+    drop(stmt);
+
+    let mut report = ReencodeReport {
+        converted: 0,
+        skipped: 0,
+        failed: 0,
+        bytes_saved: 0,
+    };
+
+    for (id, path, _) in images {
+        match reencode_png_best(&path) {
+            Ok(Some(saved)) => {
+                report.converted += 1;
+                report.bytes_saved += saved;
+                let new_size = std::fs::metadata(&path).ok().map(|m| m.len() as i64);
+                let _ = conn.execute(
+                    "UPDATE clipboard_history SET mime_type = 'image/png', image_bytes = ?1 WHERE id = ?2",
+                    params![new_size, id],
+                );
+            }
+            Ok(None) => report.skipped += 1,
+            Err(e) => {
+                eprintln!("[Clipboard] Failed to re-encode {}: {}", path, e);
+                report.failed += 1;
+            }
+        }
+    }
+
+    println!(
+        "[Clipboard] Re-encoded {} images, saved {} bytes ({} skipped, {} failed)",
+        report.converted, report.bytes_saved, report.skipped, report.failed
+    );
+
+    Ok(report)
+}
+
+/// 以最高压缩率重写 PNG，只有变小时才替换原文件
+/// 返回节省的字节数，没有收益返回 None
+fn reencode_png_best(path: &str) -> Result<Option<u64>, String> {
+    let original = std::fs::read(path).map_err(|e| format!("Failed to read image: {}", e))?;
+
+    let decoder = png::Decoder::new(&original[..]);
+    let mut reader = decoder
+        .read_info()
+        .map_err(|e| format!("Failed to decode PNG: {}", e))?;
+    // 保留物理分辨率（pHYs），重编码不应丢失 DPI 信息
+    let pixel_dims = reader.info().pixel_dims;
+    let mut buf = vec![0; reader.output_buffer_size()];
+    let info = reader
+        .next_frame(&mut buf)
+        .map_err(|e| format!("Failed to read PNG frame: {}", e))?;
+
+    let mut encoded = Vec::new();
+    {
+        let mut encoder = png::Encoder::new(&mut encoded, info.width, info.height);
+        encoder.set_color(info.color_type);
+        encoder.set_depth(info.bit_depth);
+        encoder.set_compression(png::Compression::Best);
+        if let Some(dims) = pixel_dims {
+            encoder.set_pixel_dims(Some(dims));
+        }
+        let mut writer = encoder
+            .write_header()
+            .map_err(|e| format!("Failed to write PNG header: {}", e))?;
+        writer
+            .write_image_data(&buf[..info.buffer_size()])
+            .map_err(|e| format!("Failed to write PNG data: {}", e))?;
+    }
+
+    if encoded.len() >= original.len() {
+        return Ok(None);
+    }
+
+    let saved = (original.len() - encoded.len()) as u64;
+    std::fs::write(path, encoded).map_err(|e| format!("Failed to write image: {}", e))?;
+    Ok(Some(saved))
+}
+
+/// 重复项清理结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DuplicateReport {
+    /// 发现的重复内容组数
+    pub groups_found: u32,
+    /// 被删除的多余行数
+    pub items_removed: u32,
+}
+
+/// 校验历史中是否存在完全重复的行（导入会绕过逐条插入的去重）
+/// 返回 (内容哈希, 内容类型) 列表，空表示没有重复
+pub fn verify_no_duplicates(app_data_dir: &PathBuf) -> Result<Vec<(String, String)>, String> {
+    let conn = db::get_readonly_connection(app_data_dir)?;
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT content, content_type FROM clipboard_history
+             GROUP BY content, content_type
+             HAVING COUNT(*) > 1",
+        )
+        .map_err(|e| format!("Failed to prepare duplicate check: {}", e))?;
+
+    let rows = stmt
+        .query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })
+        .map_err(|e| format!("Failed to query duplicates: {}", e))?;
+
+    let mut duplicates = Vec::new();
+    for row in rows {
+        let (content, content_type) =
+            row.map_err(|e| format!("Failed to read duplicate row: {}", e))?;
+        duplicates.push((content_hash(&content), content_type));
+    }
+    Ok(duplicates)
+}
+
+/// 清理完全重复的行：每组保留最优一条（收藏优先，其次最新），
+/// 收藏状态和备注合并到保留项。导入后运行可恢复去重不变式。
+pub fn resolve_duplicates(app_data_dir: &PathBuf) -> Result<DuplicateReport, String> {
+    let conn = db::get_connection(app_data_dir)?;
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT content, content_type FROM clipboard_history
+             GROUP BY content, content_type
+             HAVING COUNT(*) > 1",
+        )
+        .map_err(|e| format!("Failed to prepare duplicate check: {}", e))?;
+
+    let groups: Vec<(String, String)> = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+        .map_err(|e| format!("Failed to query duplicates: {}", e))?
+        .filter_map(|r| r.ok())
+        .collect();
+
+    drop(stmt);
+
+    let mut report = DuplicateReport {
+        groups_found: groups.len() as u32,
+        items_removed: 0,
+    };
+
+    for (content, content_type) in groups {
+        let mut stmt = conn
+            .prepare(&format!(
+                "SELECT {} FROM clipboard_history WHERE content = ?1 AND content_type = ?2",
+                ITEM_COLUMNS
+            ))
+            .map_err(|e| format!("Failed to prepare group query: {}", e))?;
+
+        let mut group: Vec<ClipboardItem> = stmt
+            .query_map(params![content, content_type], map_item_row)
+            .map_err(|e| format!("Failed to query duplicate group: {}", e))?
+            .filter_map(|r| r.ok())
+            .collect();
+
+        drop(stmt);
+
+        if group.len() < 2 {
+            continue;
+        }
+
+        // 收藏优先，其次保留较新的一条
+        group.sort_by(|a, b| {
+            b.is_favorite
+                .cmp(&a.is_favorite)
+                .then(b.created_at.cmp(&a.created_at))
+        });
+
+        let survivor = &group[0];
+        let merged_favorite = group.iter().any(|item| item.is_favorite);
+        let merged_note = survivor
+            .note
+            .clone()
+            .or_else(|| group.iter().find_map(|item| item.note.clone()));
+        let merged_tags = merge_tags(&group);
+
+        conn.execute(
+            "UPDATE clipboard_history SET is_favorite = ?1, note = ?2, tags = ?3 WHERE id = ?4",
+            params![
+                if merged_favorite { 1 } else { 0 },
+                merged_note,
+                merged_tags,
+                survivor.id
+            ],
+        )
+        .map_err(|e| format!("Failed to update surviving item: {}", e))?;
+
+        for item in &group[1..] {
+            conn.execute(
+                "DELETE FROM clipboard_history WHERE id = ?1",
+                params![item.id],
+            )
+            .map_err(|e| format!("Failed to delete duplicate item {}: {}", item.id, e))?;
+            report.items_removed += 1;
+        }
+    }
+
+    println!(
+        "[Clipboard] Resolved {} duplicate groups ({} items removed)",
+        report.groups_found, report.items_removed
+    );
+
+    Ok(report)
+}
+
+/// 计算图片项的主色调（平均色，忽略完全透明的像素），结果缓存到数据库
+pub fn compute_dominant_color(id: String, app_data_dir: &PathBuf) -> Result<String, String> {
+    let conn = db::get_connection(app_data_dir)?;
+
+    let (content, content_type, cached): (String, String, Option<String>) = conn
+        .query_row(
+            "SELECT content, content_type, dominant_color FROM clipboard_history WHERE id = ?1",
+            params![id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )
+        .optional()
+        .map_err(|e| format!("Failed to load clipboard item: {}", e))?
+        .ok_or_else(|| format!("Clipboard item {} not found", id))?;
+
+    if content_type != "image" {
+        return Err(format!("Clipboard item {} is not an image", id));
+    }
+
+    // 已有缓存直接返回
+    if let Some(color) = cached {
+        return Ok(color);
+    }
+
+    let image_data = std::fs::read(&content)
+        .map_err(|e| format!("Failed to read image file {}: {}", content, e))?;
+
+    let color = dominant_color_of_png(&image_data)?;
+
+    conn.execute(
+        "UPDATE clipboard_history SET dominant_color = ?1 WHERE id = ?2",
+        params![color, id],
+    )
+    .map_err(|e| format!("Failed to store dominant color: {}", e))?;
+
+    Ok(color)
+}
+
+/// 对 PNG 数据降采样后计算平均色，返回 "#rrggbb"
+fn dominant_color_of_png(image_data: &[u8]) -> Result<String, String> {
+    let decoder = png::Decoder::new(image_data);
+    let mut reader = decoder
+        .read_info()
+        .map_err(|e| format!("Failed to decode PNG: {}", e))?;
+    let mut buf = vec![0; reader.output_buffer_size()];
+    let info = reader
+        .next_frame(&mut buf)
+        .map_err(|e| format!("Failed to read PNG frame: {}", e))?;
+
+    let (channels, has_alpha) = match info.color_type {
+        png::ColorType::Rgba => (4usize, true),
+        png::ColorType::Rgb => (3usize, false),
+        other => return Err(format!("Unsupported PNG color type: {:?}", other)),
+    };
+
+    let width = info.width as usize;
+    let height = info.height as usize;
+    if width == 0 || height == 0 {
+        return Err("Empty image".to_string());
+    }
+
+    // 降采样：每个方向最多取 64 个采样点，避免大图全量扫描
+    let step_x = (width / 64).max(1);
+    let step_y = (height / 64).max(1);
+
+    let mut r_sum: u64 = 0;
+    let mut g_sum: u64 = 0;
+    let mut b_sum: u64 = 0;
+    let mut count: u64 = 0;
+
+    for y in (0..height).step_by(step_y) {
+        for x in (0..width).step_by(step_x) {
+            let offset = (y * width + x) * channels;
+            if offset + channels > buf.len() {
+                continue;
+            }
+            // 完全透明的像素不参与统计
+            if has_alpha && buf[offset + 3] == 0 {
+                continue;
+            }
+            r_sum += buf[offset] as u64;
+            g_sum += buf[offset + 1] as u64;
+            b_sum += buf[offset + 2] as u64;
+            count += 1;
+        }
+    }
+
+    if count == 0 {
+        return Err("Image has no opaque pixels".to_string());
+    }
+
+    Ok(format!(
+        "#{:02x}{:02x}{:02x}",
+        r_sum / count,
+        g_sum / count,
+        b_sum / count
+    ))
+}
+
+/// 删除指定来源应用的所有历史记录，返回删除的数量
+/// include_favorites 为 false 时保留收藏项
+pub fn delete_items_by_source(
+    source_app: &str,
+    app_data_dir: &PathBuf,
+    include_favorites: bool,
+) -> Result<u32, String> {
+    let conn = db::get_connection(app_data_dir)?;
+
+    let favorite_clause = if include_favorites {
+        ""
+    } else {
+        " AND is_favorite = 0"
+    };
+
+    // 先查询要删除的图片项（去重），删除记录后再清理文件
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT DISTINCT content FROM clipboard_history
+             WHERE source_app = ?1 AND content_type = 'image'{}",
+            favorite_clause
+        ))
+        .map_err(|e| format!("Failed to prepare query: {}", e))?;
+
+    let image_paths: Vec<String> = stmt
+        .query_map(params![source_app], |row| row.get(0))
+        .map_err(|e| format!("Failed to query image paths: {}", e))?
+        .filter_map(|r| r.ok())
+        .collect();
+
+    drop(stmt);
+
     let affected = conn
-        .execute("DELETE FROM clipboard_history WHERE id = ?1", params![id])
-        .map_err(|e| format!("Failed to delete clipboard item: {}", e))?;
-    if affected == 0 {
-        return Err("Clipboard item not found".to_string());
+        .execute(
+            &format!(
+                "DELETE FROM clipboard_history WHERE source_app = ?1{}",
+                favorite_clause
+            ),
+            params![source_app],
+        )
+        .map_err(|e| format!("Failed to delete items by source: {}", e))?;
+
+    // 清理不再被任何记录引用的图片文件
+    for image_path in image_paths {
+        let ref_count: i64 = conn
+            .query_row(
+                "SELECT COUNT(*) FROM clipboard_history WHERE content = ?1 AND content_type = 'image'",
+                params![image_path],
+                |row| row.get(0),
+            )
+            .unwrap_or(0);
+
+        if ref_count > 0 {
+            continue;
+        }
+
+        let path = std::path::Path::new(&image_path);
+        if path.exists() {
+            if let Err(e) = std::fs::remove_file(path) {
+                eprintln!("[Clipboard] Failed to delete image file {}: {}", image_path, e);
+            } else {
+                println!("[Clipboard] Deleted image file: {}", image_path);
+            }
+        }
+        remove_thumbnail_for(&image_path);
+    }
+
+    println!("[Clipboard] Deleted {} items from source {}", affected, source_app);
+
+    Ok(affected as u32)
+}
+
+/// 分页获取剪切板历史（键集分页，监控新增内容时页内容不漂移）
+pub fn get_clipboard_items_page(
+    cursor: Option<String>,
+    limit: u32,
+    app_data_dir: &PathBuf,
+) -> Result<ClipboardPage, String> {
+    query_page(None, cursor, limit, app_data_dir)
+}
+
+/// 分页搜索剪切板历史（键集分页）
+pub fn search_clipboard_items_page(
+    query: &str,
+    cursor: Option<String>,
+    limit: u32,
+    app_data_dir: &PathBuf,
+) -> Result<ClipboardPage, String> {
+    query_page(Some(query), cursor, limit, app_data_dir)
+}
+
+fn query_page(
+    query: Option<&str>,
+    cursor: Option<String>,
+    limit: u32,
+    app_data_dir: &PathBuf,
+) -> Result<ClipboardPage, String> {
+    let conn = db::get_readonly_connection(app_data_dir)?;
+    let limit = if limit == 0 { 50 } else { limit.min(500) };
+
+    // 开启加密时 SQL 的 LIKE 对密文无效，退回整表搜索
+    // （含密文合并），在解密后的结果上按同样的键集规则分页
+    if let Some(q) = query {
+        if clipboard_encryption_enabled(&conn) {
+            let mut items = search_clipboard_items(q, None, None, app_data_dir)?;
+            items.sort_by(|a, b| {
+                b.created_at
+                    .cmp(&a.created_at)
+                    .then_with(|| b.id.cmp(&a.id))
+            });
+            if let Some(cursor) = cursor.as_deref() {
+                let (ts, id) = decode_cursor(cursor)?;
+                items.retain(|item| (item.created_at as i64, item.id.as_str()) < (ts, id.as_str()));
+            }
+            items.truncate(limit as usize);
+            let next_cursor = if items.len() == limit as usize {
+                items.last().map(encode_cursor)
+            } else {
+                None
+            };
+            return Ok(ClipboardPage { items, next_cursor });
+        }
+    }
+
+    let mut sql = format!("SELECT {} FROM clipboard_history", ITEM_COLUMNS);
+    let mut clauses: Vec<String> = Vec::new();
+    let mut args: Vec<Box<dyn rusqlite::ToSql>> = Vec::new();
+
+    if let Some(q) = query {
+        clauses.push(format!("lower(content) LIKE ?{}", args.len() + 1));
+        args.push(Box::new(format!(
+            "%{}%",
+            normalize_query(q, app_data_dir).to_lowercase()
+        )));
+    }
+
+    if let Some(cursor) = cursor.as_deref() {
+        // 键集分页：严格小于游标位置，新增的项不会让页面漂移或重复
+        let (ts, id) = decode_cursor(cursor)?;
+        clauses.push(format!(
+            "(created_at, id) < (?{}, ?{})",
+            args.len() + 1,
+            args.len() + 2
+        ));
+        args.push(Box::new(ts));
+        args.push(Box::new(id));
+    }
+
+    if !clauses.is_empty() {
+        sql.push_str(" WHERE ");
+        sql.push_str(&clauses.join(" AND "));
+    }
+    sql.push_str(&format!(
+        " ORDER BY created_at DESC, id DESC LIMIT ?{}",
+        args.len() + 1
+    ));
+    args.push(Box::new(limit as i64));
+
+    let mut stmt = conn
+        .prepare(&sql)
+        .map_err(|e| format!("Failed to prepare clipboard page query: {}", e))?;
+
+    let rows = stmt
+        .query_map(
+            rusqlite::params_from_iter(args.iter().map(|a| a.as_ref())),
+            map_item_row,
+        )
+        .map_err(|e| format!("Failed to iterate clipboard page: {}", e))?;
+
+    let mut items = Vec::new();
+    for row in rows {
+        items.push(row.map_err(|e| format!("Failed to read clipboard row: {}", e))?);
+    }
+
+    // 只有整页装满时才可能有下一页
+    let next_cursor = if items.len() == limit as usize {
+        items.last().map(encode_cursor)
+    } else {
+        None
+    };
+
+    apply_previews(&mut items, app_data_dir);
+    Ok(ClipboardPage { items, next_cursor })
+}
+
+#[cfg(target_os = "linux")]
+pub mod primary_monitor {
+    use super::*;
+    use std::process::Command;
+    use std::thread;
+    use std::time::Duration;
+
+    /// 启动 PRIMARY 选区监控线程（Linux 中键粘贴的来源）
+    /// PRIMARY 随每次划选变化，所以做了强去抖：
+    /// 连续两次轮询内容一致（选区已稳定）且与上次入库不同才记录
+    pub fn start_primary_monitor(app_data_dir: PathBuf) -> Result<(), String> {
+        thread::spawn(move || {
+            let mut last_seen = String::new();
+            let mut last_stored = String::new();
+
+            loop {
+                thread::sleep(Duration::from_millis(get_clipboard_poll_interval()));
+
+                if is_clipboard_monitor_paused() {
+                    continue;
+                }
+
+                let content = match read_primary_selection() {
+                    Some(content) => content,
+                    None => continue,
+                };
+
+                if content.is_empty() || content == last_stored {
+                    last_seen = content;
+                    continue;
+                }
+
+                // 第一次看到新内容先记下，下一轮还一样才认为选区稳定
+                if content != last_seen {
+                    last_seen = content;
+                    continue;
+                }
+
+                match add_clipboard_item(content.clone(), "text".to_string(), &app_data_dir) {
+                    Ok(item) => {
+                        // 标记来源为 PRIMARY 选区，便于过滤
+                        if let Ok(conn) = db::get_connection(&app_data_dir) {
+                            let _ = conn.execute(
+                                "UPDATE clipboard_history SET source_app = 'PRIMARY' WHERE id = ?1",
+                                params![item.id],
+                            );
+                        }
+                        monitor_log(
+                            LogLevel::Info,
+                            "capture",
+                            Some("text"),
+                            "Captured PRIMARY selection item",
+                        );
+                        last_stored = content;
+                    }
+                    Err(e) => monitor_log(
+                        LogLevel::Error,
+                        "store",
+                        Some("text"),
+                        &format!("Failed to add PRIMARY selection item: {}", e),
+                    ),
+                }
+            }
+        });
+
+        Ok(())
+    }
+
+    /// 读取 PRIMARY 选区文本，读不到（无 X11 / 非文本）返回 None
+    fn read_primary_selection() -> Option<String> {
+        let output = Command::new("xclip")
+            .arg("-selection")
+            .arg("primary")
+            .arg("-o")
+            .output()
+            .ok()?;
+
+        if !output.status.success() || output.stdout.is_empty() {
+            return None;
+        }
+
+        String::from_utf8(output.stdout).ok()
+    }
+}
+
+#[cfg(target_os = "macos")]
+pub mod monitor {
+    use super::*;
+    use std::process::Command;
+    use std::thread;
+    use std::time::Duration;
+
+    /// 通过 pbpaste 读取系统剪贴板文本
+    pub fn get_clipboard_text() -> Result<String, String> {
+        let output = Command::new("pbpaste")
+            .output()
+            .map_err(|e| format!("Failed to run pbpaste: {}", e))?;
+
+        if !output.status.success() {
+            return Err("pbpaste returned an error".to_string());
+        }
+
+        String::from_utf8(output.stdout).map_err(|e| format!("Clipboard text is not UTF-8: {}", e))
+    }
+
+    /// 启动剪贴板监控线程（轮询 pbpaste；图片捕获暂未支持）
+    /// 与 Windows 版签名一致，调用方不需要按平台区分
+    pub fn start_clipboard_monitor(app_data_dir: PathBuf) -> Result<(), String> {
+        thread::spawn(move || {
+            monitor_log(
+                LogLevel::Info,
+                "init",
+                None,
+                "Clipboard monitor started (polling)",
+            );
+
+            loop {
+                thread::sleep(Duration::from_millis(get_clipboard_poll_interval()));
+
+                if is_clipboard_monitor_paused() {
+                    continue;
+                }
+
+                // 剪贴板为空或读取失败都静默跳过，下一轮再试
+                let content = match get_clipboard_text() {
+                    Ok(content) => content,
+                    Err(_) => continue,
+                };
+
+                if content.is_empty() || content == monitor_dedup_last_text() {
+                    continue;
+                }
+
+                match add_clipboard_item(content.clone(), "text".to_string(), &app_data_dir) {
+                    Ok(_) => monitor_log(
+                        LogLevel::Info,
+                        "capture",
+                        Some("text"),
+                        "Captured text clipboard item",
+                    ),
+                    Err(e) => monitor_log(
+                        LogLevel::Error,
+                        "store",
+                        Some("text"),
+                        &format!("Failed to add text clipboard item: {}", e),
+                    ),
+                }
+                set_monitor_dedup_last_text(&content);
+            }
+        });
+
+        Ok(())
+    }
+}
+
+#[cfg(target_os = "linux")]
+pub mod monitor {
+    use super::*;
+    use std::process::Command;
+    use std::thread;
+    use std::time::Duration;
+
+    /// 通过 xclip 读取 CLIPBOARD 选区的文本
+    pub fn get_clipboard_text() -> Result<String, String> {
+        let output = Command::new("xclip")
+            .arg("-selection")
+            .arg("clipboard")
+            .arg("-o")
+            .output()
+            .map_err(|e| format!("Failed to run xclip: {}", e))?;
+
+        if !output.status.success() {
+            return Err("xclip returned an error".to_string());
+        }
+
+        String::from_utf8(output.stdout).map_err(|e| format!("Clipboard text is not UTF-8: {}", e))
+    }
+
+    /// 启动剪贴板监控线程（轮询 xclip；图片捕获暂未支持）
+    /// 与 Windows 版签名一致，调用方不需要按平台区分
+    pub fn start_clipboard_monitor(app_data_dir: PathBuf) -> Result<(), String> {
+        thread::spawn(move || {
+            monitor_log(
+                LogLevel::Info,
+                "init",
+                None,
+                "Clipboard monitor started (polling)",
+            );
+
+            loop {
+                thread::sleep(Duration::from_millis(get_clipboard_poll_interval()));
+
+                if is_clipboard_monitor_paused() {
+                    continue;
+                }
+
+                // 剪贴板为空或 xclip 不可用都静默跳过，下一轮再试
+                let content = match get_clipboard_text() {
+                    Ok(content) => content,
+                    Err(_) => continue,
+                };
+
+                if content.is_empty() || content == monitor_dedup_last_text() {
+                    continue;
+                }
+
+                match add_clipboard_item(content.clone(), "text".to_string(), &app_data_dir) {
+                    Ok(_) => monitor_log(
+                        LogLevel::Info,
+                        "capture",
+                        Some("text"),
+                        "Captured text clipboard item",
+                    ),
+                    Err(e) => monitor_log(
+                        LogLevel::Error,
+                        "store",
+                        Some("text"),
+                        &format!("Failed to add text clipboard item: {}", e),
+                    ),
+                }
+                set_monitor_dedup_last_text(&content);
+            }
+        });
+
+        Ok(())
+    }
+}
+
+#[cfg(target_os = "windows")]
+pub mod monitor {
+    use super::*;
+    use std::thread;
+    use std::os::windows::ffi::{OsStrExt, OsStringExt};
+    use std::ffi::OsStr;
+    use std::ptr;
+    use windows_sys::Win32::System::DataExchange::{
+        GetClipboardData, IsClipboardFormatAvailable, OpenClipboard, CloseClipboard,
+        AddClipboardFormatListener, RemoveClipboardFormatListener, EmptyClipboard,
+        SetClipboardData,
+    };
+    use windows_sys::Win32::System::Memory::{GlobalLock, GlobalUnlock, GlobalSize};
+    use windows_sys::Win32::Foundation::{HWND, HINSTANCE, LPARAM, WPARAM};
+    use windows_sys::Win32::UI::WindowsAndMessaging::{
+        CreateWindowExW, DefWindowProcW, DispatchMessageW, GetForegroundWindow, GetMessageW,
+        GetShellWindow, GetWindowRect, RegisterClassExW, TranslateMessage, MSG, WNDCLASSEXW,
+        WM_CLIPBOARDUPDATE, WM_QUIT, WS_OVERLAPPED, CS_HREDRAW, CS_VREDRAW,
+    };
+    use windows_sys::Win32::Graphics::Gdi::{
+        GetDIBits, GetMonitorInfoW, MonitorFromWindow, BITMAPINFO, BITMAPINFOHEADER, BI_RGB,
+        DIB_RGB_COLORS, MONITORINFO, MONITOR_DEFAULTTONEAREST,
+    };
+    use windows_sys::Win32::Foundation::RECT;
+
+    const CF_TEXT: u32 = 1;
+    const CF_UNICODETEXT: u32 = 13;
+    const CF_DIB: u32 = 8;
+    const CF_BITMAP: u32 = 2;
+    const CF_HDROP: u32 = 15;
+
+    /// 启动剪切板监控线程（使用 Windows 消息机制，完全避免冲突）
+    pub fn start_clipboard_monitor(app_data_dir: PathBuf) -> Result<(), String> {
+        thread::spawn(move || {
+            // 创建隐藏的消息窗口来接收剪贴板更新通知
+            let hwnd = match create_message_window() {
+                Ok(hwnd) => hwnd,
+                Err(e) => {
+                    monitor_log(
+                        LogLevel::Error,
+                        "init",
+                        None,
+                        &format!("Failed to create message window: {}", e),
+                    );
+                    // 窗口创建失败的环境（如某些精简会话）退回轮询
+                    run_polling_fallback(&app_data_dir);
+                    return;
+                }
+            };
+
+            // 注册剪贴板格式监听器（不需要打开剪贴板，完全避免冲突）
+            unsafe {
+                if AddClipboardFormatListener(hwnd) == 0 {
+                    monitor_log(
+                        LogLevel::Error,
+                        "init",
+                        None,
+                        "Failed to add clipboard format listener",
+                    );
+                    run_polling_fallback(&app_data_dir);
+                    return;
+                }
+            }
+
+            monitor_log(LogLevel::Info, "init", None, "Clipboard monitor started");
+
+            // 图片目录持续创建失败时退避，避免每次更新都撞同一个错误
+            let mut image_dir_retry_after: u64 = 0;
+
+            // 消息循环：只在收到剪贴板更新通知时才读取剪贴板
+            let mut msg: MSG = unsafe { std::mem::zeroed() };
+            loop {
+                unsafe {
+                    let result = GetMessageW(&mut msg, hwnd, 0, 0);
+                    if result == 0 || result == -1 {
+                        // WM_QUIT 或错误
+                        break;
+                    }
+
+                    if msg.message == WM_CLIPBOARDUPDATE
+                        && !is_clipboard_monitor_paused()
+                        && !capture_suppressed(&app_data_dir)
+                        && !capture_excluded_by_class(&app_data_dir)
+                    {
+                        // 剪贴板内容已改变，现在可以安全地读取
+                        // 因为这是系统通知，说明剪贴板操作已完成
+                        capture_clipboard_update(&app_data_dir, &mut image_dir_retry_after);
+                    }
+
+                    TranslateMessage(&msg);
+                    DispatchMessageW(&msg);
+                }
+            }
+
+            // 清理：移除监听器
+            unsafe {
+                RemoveClipboardFormatListener(hwnd);
+            }
+        });
+        
+        Ok(())
     }
-    
-    if let Some((content, content_type)) = item {
-        // 如果是图片类型，检查是否还有其他记录引用这个文件
-        if content_type == "image" {
-            let ref_count: i64 = conn
-                .query_row(
-                    "SELECT COUNT(*) FROM clipboard_history WHERE content = ?1 AND content_type = 'image'",
-                    params![content],
-                    |row| row.get(0),
-                )
-                .unwrap_or(0);
-            
-            println!("[Clipboard] Image {} has {} remaining references", content, ref_count);
-            
-            // 只有当没有其他记录引用时才删除文件
-            if ref_count == 0 {
-                let image_path = std::path::Path::new(&content);
-                println!("[Clipboard] Deleting image: {}", content);
-                
-                if image_path.exists() {
-                    // 重试删除最多3次
-                    let mut retry = 0;
-                    let max_retries = 3;
-                    
-                    while retry < max_retries {
-                        match std::fs::remove_file(image_path) {
-                            Ok(_) => {
-                                println!("[Clipboard] Successfully deleted image file: {}", content);
-                                break;
+
+    /// 事件驱动监控的显式入口；start_clipboard_monitor 默认就是事件驱动，
+    /// 仅在消息窗口创建失败的环境退回轮询
+    pub fn start_clipboard_monitor_event_driven(app_data_dir: PathBuf) -> Result<(), String> {
+        start_clipboard_monitor(app_data_dir)
+    }
+
+    /// 处理一次剪贴板更新：解析来源、按格式优先级捕获文件/文本/图片
+    /// 事件驱动与轮询回退共用此路径，去重行为保持一致
+    fn capture_clipboard_update(app_data_dir: &PathBuf, image_dir_retry_after: &mut u64) {
+        unsafe {
+            // 复制发起方通常仍是前台窗口，先解析来源再读内容
+            let source_app = resolve_source_app(app_data_dir);
+
+            // 一次复制同时携带文本和图片时，按用户的格式优先级挑主格式
+            // 文件复制（CF_HDROP）优先于两者：资源管理器同时放的文本只是文件名
+            let file_available = IsClipboardFormatAvailable(CF_HDROP) != 0;
+            let text_available = IsClipboardFormatAvailable(CF_UNICODETEXT) != 0
+                || IsClipboardFormatAvailable(CF_TEXT) != 0;
+            let image_available = IsClipboardFormatAvailable(CF_DIB) != 0
+                || IsClipboardFormatAvailable(CF_BITMAP) != 0;
+            let priority = crate::settings::load_settings(app_data_dir)
+                .map(|s| s.clipboard_format_priority)
+                .unwrap_or_default();
+            let text_first =
+                format_rank(&priority, "text") < format_rank(&priority, "image");
+            let capture_text =
+                !file_available && text_available && (!image_available || text_first);
+            let capture_image =
+                !file_available && image_available && (!text_available || !text_first);
+
+            // 检查文件复制，整组路径按一条记录处理
+            if file_available {
+                match get_clipboard_file_paths() {
+                    Ok(paths) if !paths.is_empty() => {
+                        // 按完整路径列表去重，重新复制同一批文件不会重复入库
+                        let joined = paths.join("\n");
+                        if joined != monitor_dedup_last_file() {
+                            match add_clipboard_file_paths(paths, app_data_dir) {
+                                Ok(items) => {
+                                    monitor_log(
+                                        LogLevel::Info,
+                                        "capture",
+                                        Some("file"),
+                                        "Captured file clipboard item",
+                                    );
+                                    if let Some(app) = &source_app {
+                                        for item in &items {
+                                            let _ = record_source_app(&item.id, app, app_data_dir);
+                                            let _ = apply_source_note(&item.id, app, app_data_dir);
+                                        }
+                                    }
+                                }
+                                Err(e) => monitor_log(
+                                    LogLevel::Error,
+                                    "store",
+                                    Some("file"),
+                                    &format!("Failed to add file clipboard item: {}", e),
+                                ),
                             }
-                            Err(e) => {
-                                retry += 1;
-                                eprintln!("[Clipboard] Failed to delete image file {} (attempt {}): {}", content, retry, e);
-                                if retry < max_retries {
-                                    std::thread::sleep(std::time::Duration::from_millis(100));
+                            set_monitor_dedup_last_file(&joined);
+                        }
+                    }
+                    Ok(_) => {}
+                    Err(e) => monitor_log(
+                        LogLevel::Warn,
+                        "read",
+                        Some("file"),
+                        &format!("Failed to read clipboard files: {}", e),
+                    ),
+                }
+            }
+
+            // 检查文本内容
+            if capture_text {
+                match get_clipboard_text() {
+                    Ok(content) => {
+                        if !content.is_empty() && content != monitor_dedup_last_text() {
+                            // 同一次复制可能带 HTML/RTF 变体，入库后挂到条目上
+                            let rich = get_clipboard_rich_text().unwrap_or(None);
+                            match add_clipboard_item(content.clone(), "text".to_string(), app_data_dir) {
+                                Ok(item) => {
+                                    monitor_log(
+                                        LogLevel::Info,
+                                        "capture",
+                                        Some("text"),
+                                        "Captured text clipboard item",
+                                    );
+                                    if let Some((rich_content, rich_format)) = &rich {
+                                        let _ = record_rich_content(
+                                            &item.id,
+                                            rich_content,
+                                            rich_format,
+                                            app_data_dir,
+                                        );
+                                    }
+                                    if let Some(app) = &source_app {
+                                        let _ = record_source_app(&item.id, app, app_data_dir);
+                                        let _ = apply_source_note(&item.id, app, app_data_dir);
+                                    }
                                 }
+                                Err(e) => monitor_log(
+                                    LogLevel::Error,
+                                    "store",
+                                    Some("text"),
+                                    &format!("Failed to add text clipboard item: {}", e),
+                                ),
+                            }
+                            set_monitor_dedup_last_text(&content);
+                        }
+                    }
+                    Err(e) => monitor_log(
+                        LogLevel::Warn,
+                        "read",
+                        Some("text"),
+                        &format!("Failed to read clipboard text: {}", e),
+                    ),
+                }
+            }
+
+            // 检查图片内容；保存目录不可用时退避并暴露原因
+            if capture_image && now_ts() >= *image_dir_retry_after {
+                let images_dir = app_data_dir.join("clipboard_images");
+                if let Err(e) = std::fs::create_dir_all(&images_dir) {
+                    let status = format!("Cannot save images: {}", e);
+                    monitor_log(LogLevel::Error, "store", Some("image"), &status);
+                    set_monitor_status(Some(status));
+                    *image_dir_retry_after = now_ts() + 60;
+                } else if !image_save_blocked_by_disk(&images_dir, app_data_dir) {
+                    set_monitor_status(None);
+                    if let Ok((image_path, image_hash)) = get_clipboard_image(app_data_dir) {
+                        if !image_path.is_empty() {
+                            // 按像素内容哈希去重，路径每次都可能不同
+                            if image_hash != monitor_dedup_last_image() {
+                                match add_clipboard_item(image_path.clone(), "image".to_string(), app_data_dir) {
+                                    Ok(item) => {
+                                        monitor_log(
+                                            LogLevel::Info,
+                                            "capture",
+                                            Some("image"),
+                                            "Captured image clipboard item",
+                                        );
+                                        if let Some(app) = &source_app {
+                                            let _ = record_source_app(&item.id, app, app_data_dir);
+                                            let _ = apply_source_note(&item.id, app, app_data_dir);
+                                        }
+                                    }
+                                    Err(e) => monitor_log(
+                                        LogLevel::Error,
+                                        "store",
+                                        Some("image"),
+                                        &format!("Failed to add image clipboard item: {}", e),
+                                    ),
+                                }
+                                set_monitor_dedup_last_image(&image_hash);
                             }
                         }
                     }
-                } else {
-                    eprintln!("[Clipboard] Image file not found: {}", content);
                 }
             }
         }
     }
-    
-    Ok(())
-}
 
-/// 清空剪切板历史
-pub fn clear_clipboard_history(app_data_dir: &PathBuf) -> Result<(), String> {
-    let conn = db::get_connection(app_data_dir)?;
-    
-    // 先查询所有要删除的图片项（去重）
-    let mut stmt = conn
-        .prepare("SELECT DISTINCT content FROM clipboard_history WHERE is_favorite = 0 AND content_type = 'image'")
-        .map_err(|e| format!("Failed to prepare query: {}", e))?;
-    
-    let image_paths: Vec<String> = stmt
-        .query_map([], |row| row.get(0))
-        .map_err(|e| format!("Failed to query image paths: {}", e))?
-        .filter_map(|r| r.ok())
-        .collect();
-    
-    // 释放语句，避免持有数据库连接
-    drop(stmt);
-    
-    println!("[Clipboard] Found {} unique image files to check", image_paths.len());
-    
-    // 先删除数据库记录
-    conn.execute("DELETE FROM clipboard_history WHERE is_favorite = 0", [])
-        .map_err(|e| format!("Failed to clear clipboard history: {}", e))?;
-    
-    // 然后检查并删除图片文件
-    let mut deleted_count = 0;
-    for image_path in image_paths {
-        // 检查是否还有收藏记录引用这个图片
-        let ref_count: i64 = conn
-            .query_row(
-                "SELECT COUNT(*) FROM clipboard_history WHERE content = ?1 AND content_type = 'image'",
-                params![image_path],
-                |row| row.get(0),
+    /// 事件通知不可用时的轮询回退：按可调间隔检查剪贴板，
+    /// 去重依赖与事件路径相同的共享状态
+    fn run_polling_fallback(app_data_dir: &PathBuf) {
+        monitor_log(
+            LogLevel::Warn,
+            "init",
+            None,
+            "Falling back to clipboard polling",
+        );
+
+        let mut image_dir_retry_after: u64 = 0;
+        loop {
+            thread::sleep(std::time::Duration::from_millis(get_clipboard_poll_interval()));
+
+            if is_clipboard_monitor_paused()
+                || capture_suppressed(app_data_dir)
+                || capture_excluded_by_class(app_data_dir)
+            {
+                continue;
+            }
+
+            capture_clipboard_update(app_data_dir, &mut image_dir_retry_after);
+        }
+    }
+
+    /// 查询路径所在卷对当前用户可用的字节数
+    fn free_bytes_for(path: &std::path::Path) -> Option<u64> {
+        use windows_sys::Win32::Storage::FileSystem::GetDiskFreeSpaceExW;
+
+        let wide: Vec<u16> = path
+            .as_os_str()
+            .encode_wide()
+            .chain(std::iter::once(0))
+            .collect();
+
+        let mut free_bytes: u64 = 0;
+        let ok = unsafe {
+            GetDiskFreeSpaceExW(
+                wide.as_ptr(),
+                &mut free_bytes,
+                std::ptr::null_mut(),
+                std::ptr::null_mut(),
             )
+        };
+
+        if ok != 0 {
+            Some(free_bytes)
+        } else {
+            None
+        }
+    }
+
+    /// 可用空间低于用户设置的下限时跳过图片保存（文本捕获不受影响）
+    fn image_save_blocked_by_disk(images_dir: &std::path::Path, app_data_dir: &PathBuf) -> bool {
+        let min_free = crate::settings::load_settings(app_data_dir)
+            .map(|s| s.clipboard_min_free_bytes)
             .unwrap_or(0);
-        
-        if ref_count > 0 {
-            println!("[Clipboard] Skipping {} (still referenced by {} favorite(s))", image_path, ref_count);
-            continue;
+        if min_free == 0 {
+            return false;
         }
-        
-        let path = std::path::Path::new(&image_path);
-        println!("[Clipboard] Attempting to delete: {}", image_path);
-        
-        if path.exists() {
-            // 重试删除最多3次
-            let mut retry = 0;
-            let max_retries = 3;
-            let mut success = false;
-            
-            while retry < max_retries {
-                match std::fs::remove_file(path) {
-                    Ok(_) => {
-                        deleted_count += 1;
-                        println!("[Clipboard] Successfully deleted: {}", image_path);
-                        success = true;
-                        break;
-                    }
-                    Err(e) => {
-                        retry += 1;
-                        eprintln!("[Clipboard] Failed to delete image file {} (attempt {}): {}", image_path, retry, e);
-                        if retry < max_retries {
-                            std::thread::sleep(std::time::Duration::from_millis(100));
-                        }
-                    }
-                }
+
+        match free_bytes_for(images_dir) {
+            Some(free) if free < min_free => {
+                let status = format!(
+                    "Low disk space: {} bytes free, minimum {} required for images",
+                    free, min_free
+                );
+                monitor_log(LogLevel::Warn, "store", Some("image"), &status);
+                set_monitor_status(Some(status));
+                true
             }
-            
-            if !success {
-                eprintln!("[Clipboard] Gave up deleting {} after {} attempts", image_path, max_retries);
+            _ => false,
+        }
+    }
+
+    /// UWP/打包应用的通用宿主进程，其进程名无法标识真正的来源
+    const GENERIC_HOST_STEMS: [&str; 3] = ["applicationframehost", "wwahost", "runtimebroker"];
+
+    /// 解析前台窗口所属进程的完整可执行文件路径
+    pub fn foreground_app_path() -> Option<String> {
+        use windows_sys::Win32::Foundation::CloseHandle;
+        use windows_sys::Win32::System::Threading::{
+            OpenProcess, QueryFullProcessImageNameW, PROCESS_QUERY_LIMITED_INFORMATION,
+        };
+        use windows_sys::Win32::UI::WindowsAndMessaging::GetWindowThreadProcessId;
+
+        unsafe {
+            let hwnd = GetForegroundWindow();
+            if hwnd == 0 {
+                return None;
             }
-        } else {
-            eprintln!("[Clipboard] Image file not found: {}", image_path);
+
+            let mut pid: u32 = 0;
+            GetWindowThreadProcessId(hwnd, &mut pid);
+            if pid == 0 {
+                return None;
+            }
+
+            let handle = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, pid);
+            if handle == 0 {
+                return None;
+            }
+
+            let mut buf = [0u16; 1024];
+            let mut len = buf.len() as u32;
+            let ok = QueryFullProcessImageNameW(handle, 0, buf.as_mut_ptr(), &mut len);
+            CloseHandle(handle);
+
+            if ok == 0 || len == 0 {
+                return None;
+            }
+
+            let path = std::ffi::OsString::from_wide(&buf[..len as usize]);
+            Some(path.to_string_lossy().to_string())
+        }
+    }
+
+    /// 解析前台窗口所属进程的可执行文件名（不含扩展名）
+    pub fn foreground_app_name() -> Option<String> {
+        let path = foreground_app_path()?;
+        std::path::Path::new(&path)
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+    }
+
+    /// 读取前台窗口标题
+    fn foreground_window_title() -> Option<String> {
+        use windows_sys::Win32::UI::WindowsAndMessaging::GetWindowTextW;
+
+        unsafe {
+            let hwnd = GetForegroundWindow();
+            if hwnd == 0 {
+                return None;
+            }
+
+            let mut buf = [0u16; 512];
+            let len = GetWindowTextW(hwnd, buf.as_mut_ptr(), buf.len() as i32);
+            if len <= 0 {
+                return None;
+            }
+
+            let title = String::from_utf16_lossy(&buf[..len as usize]);
+            let title = title.trim();
+            if title.is_empty() {
+                None
+            } else {
+                Some(title.to_string())
+            }
+        }
+    }
+
+    /// 解析捕获来源：默认记录基名，开启设置后记录完整路径；
+    /// UWP 宿主进程（如 ApplicationFrameHost）退回窗口标题
+    fn resolve_source_app(app_data_dir: &PathBuf) -> Option<String> {
+        let full = foreground_app_path()?;
+        let stem = std::path::Path::new(&full)
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_lowercase())?;
+
+        if GENERIC_HOST_STEMS.contains(&stem.as_str()) {
+            return foreground_window_title().or(Some(full));
+        }
+
+        let full_path_mode = crate::settings::load_settings(app_data_dir)
+            .map(|s| s.clipboard_source_full_path)
+            .unwrap_or(false);
+
+        if full_path_mode {
+            Some(full)
+        } else {
+            foreground_app_name()
         }
     }
-    
-    println!("[Clipboard] Successfully deleted {} image files", deleted_count);
-    Ok(())
-}
 
-/// 搜索剪切板历史
-pub fn search_clipboard_items(query: &str, app_data_dir: &PathBuf) -> Result<Vec<ClipboardItem>, String> {
-    let conn = db::get_readonly_connection(app_data_dir)?;
+    /// 解析前台窗口的类名
+    fn foreground_window_class() -> Option<String> {
+        use windows_sys::Win32::UI::WindowsAndMessaging::GetClassNameW;
 
-    let like = format!("%{}%", query.to_lowercase());
-    let mut stmt = conn
-        .prepare(
-            "SELECT id, content, content_type, created_at, is_favorite
-             FROM clipboard_history
-             WHERE lower(content) LIKE ?1
-             ORDER BY is_favorite DESC, created_at DESC",
-        )
-        .map_err(|e| format!("Failed to prepare clipboard search: {}", e))?;
+        unsafe {
+            let hwnd = GetForegroundWindow();
+            if hwnd == 0 {
+                return None;
+            }
 
-    let rows = stmt
-        .query_map(params![like], |row| {
-            Ok(ClipboardItem {
-                id: row.get(0)?,
-                content: row.get(1)?,
-                content_type: row.get(2)?,
-                created_at: row.get::<_, i64>(3)? as u64,
-                is_favorite: row.get::<_, i64>(4)? != 0,
-            })
-        })
-        .map_err(|e| format!("Failed to iterate clipboard search: {}", e))?;
+            let mut buf = [0u16; 256];
+            let len = GetClassNameW(hwnd, buf.as_mut_ptr(), buf.len() as i32);
+            if len <= 0 {
+                return None;
+            }
 
-    let mut items = Vec::new();
-    for row in rows {
-        items.push(row.map_err(|e| format!("Failed to read clipboard row: {}", e))?);
+            Some(String::from_utf16_lossy(&buf[..len as usize]))
+        }
     }
-    Ok(items)
-}
 
-#[cfg(target_os = "windows")]
-pub mod monitor {
-    use super::*;
-    use std::thread;
-    use std::os::windows::ffi::{OsStrExt, OsStringExt};
-    use std::ffi::OsStr;
-    use std::ptr;
-    use windows_sys::Win32::System::DataExchange::{
-        GetClipboardData, IsClipboardFormatAvailable, OpenClipboard, CloseClipboard,
-        AddClipboardFormatListener, RemoveClipboardFormatListener,
-    };
-    use windows_sys::Win32::System::Memory::{GlobalLock, GlobalUnlock, GlobalSize};
-    use windows_sys::Win32::Foundation::{HWND, HINSTANCE, LPARAM, WPARAM};
-    use windows_sys::Win32::UI::WindowsAndMessaging::{
-        CreateWindowExW, DefWindowProcW, DispatchMessageW, GetMessageW,
-        RegisterClassExW, TranslateMessage, MSG, WNDCLASSEXW, WM_CLIPBOARDUPDATE, WM_QUIT,
-        WS_OVERLAPPED, CS_HREDRAW, CS_VREDRAW,
-    };
-    use windows_sys::Win32::Graphics::Gdi::{
-        GetDIBits, BITMAPINFO, BITMAPINFOHEADER, BI_RGB, DIB_RGB_COLORS,
-    };
+    /// 前台窗口类名命中排除列表时跳过捕获（在读剪贴板内容之前判断）
+    fn capture_excluded_by_class(app_data_dir: &PathBuf) -> bool {
+        let excluded = crate::settings::load_settings(app_data_dir)
+            .map(|s| s.clipboard_excluded_window_classes)
+            .unwrap_or_default();
+        if excluded.is_empty() {
+            return false;
+        }
 
-    const CF_TEXT: u32 = 1;
-    const CF_UNICODETEXT: u32 = 13;
-    const CF_DIB: u32 = 8;
-    const CF_BITMAP: u32 = 2;
+        let class = match foreground_window_class() {
+            Some(class) => class,
+            None => return false,
+        };
 
-    /// 启动剪切板监控线程（使用 Windows 消息机制，完全避免冲突）
-    pub fn start_clipboard_monitor(app_data_dir: PathBuf) -> Result<(), String> {
-        thread::spawn(move || {
-            // 创建隐藏的消息窗口来接收剪贴板更新通知
-            let hwnd = match create_message_window() {
-                Ok(hwnd) => hwnd,
-                Err(e) => {
-                    eprintln!("[Clipboard Monitor] Failed to create message window: {}", e);
-                    return;
-                }
-            };
+        let hit = excluded
+            .iter()
+            .any(|c| c.eq_ignore_ascii_case(class.trim()));
+        if hit {
+            monitor_log(
+                LogLevel::Info,
+                "capture",
+                None,
+                &format!("Skipped capture for excluded window class {}", class),
+            );
+        }
+        hit
+    }
 
-            // 注册剪贴板格式监听器（不需要打开剪贴板，完全避免冲突）
-            unsafe {
-                if AddClipboardFormatListener(hwnd) == 0 {
-                    eprintln!("[Clipboard Monitor] Failed to add clipboard format listener");
-                    return;
-                }
+    /// 判断前台窗口是否全屏覆盖其所在显示器（排除桌面本身）
+    fn is_foreground_fullscreen() -> bool {
+        unsafe {
+            let hwnd = GetForegroundWindow();
+            if hwnd == 0 || hwnd == GetShellWindow() {
+                return false;
             }
 
-            let mut last_text_content = String::new();
-            let mut last_image_hash = String::new();
+            let mut rect: RECT = std::mem::zeroed();
+            if GetWindowRect(hwnd, &mut rect) == 0 {
+                return false;
+            }
 
-            // 消息循环：只在收到剪贴板更新通知时才读取剪贴板
-            let mut msg: MSG = unsafe { std::mem::zeroed() };
-            loop {
-                unsafe {
-                    let result = GetMessageW(&mut msg, hwnd, 0, 0);
-                    if result == 0 || result == -1 {
-                        // WM_QUIT 或错误
-                        break;
-                    }
+            let hmonitor = MonitorFromWindow(hwnd, MONITOR_DEFAULTTONEAREST);
+            let mut info: MONITORINFO = std::mem::zeroed();
+            info.cbSize = std::mem::size_of::<MONITORINFO>() as u32;
+            if GetMonitorInfoW(hmonitor, &mut info) == 0 {
+                return false;
+            }
 
-                    if msg.message == WM_CLIPBOARDUPDATE {
-                        // 剪贴板内容已改变，现在可以安全地读取
-                        // 因为这是系统通知，说明剪贴板操作已完成
-                        
-                        // 检查文本内容
-                        if let Ok(content) = get_clipboard_text() {
-                            if !content.is_empty() && content != last_text_content {
-                                if let Err(e) = add_clipboard_item(content.clone(), "text".to_string(), &app_data_dir) {
-                                    eprintln!("[Clipboard Monitor] Failed to add text clipboard item: {}", e);
-                                }
-                                last_text_content = content;
-                            }
-                        }
-                        
-                        // 检查图片内容
-                        if let Ok(image_path) = get_clipboard_image(&app_data_dir) {
-                            if !image_path.is_empty() {
-                                let image_hash = format!("{}", image_path);
-                                if image_hash != last_image_hash {
-                                    if let Err(e) = add_clipboard_item(image_path.clone(), "image".to_string(), &app_data_dir) {
-                                        eprintln!("[Clipboard Monitor] Failed to add image clipboard item: {}", e);
-                                    }
-                                    last_image_hash = image_hash;
-                                }
-                            }
-                        }
-                    }
+            rect.left <= info.rcMonitor.left
+                && rect.top <= info.rcMonitor.top
+                && rect.right >= info.rcMonitor.right
+                && rect.bottom >= info.rcMonitor.bottom
+        }
+    }
 
-                    TranslateMessage(&msg);
-                    DispatchMessageW(&msg);
-                }
-            }
+    /// 全屏应用处于前台且用户启用了抑制时跳过捕获，避免干扰游戏/放映
+    fn capture_suppressed(app_data_dir: &PathBuf) -> bool {
+        if !is_foreground_fullscreen() {
+            return false;
+        }
 
-            // 清理：移除监听器
-            unsafe {
-                RemoveClipboardFormatListener(hwnd);
-            }
-        });
-        
-        Ok(())
+        let suppress = crate::settings::load_settings(app_data_dir)
+            .map(|s| s.clipboard_suppress_fullscreen)
+            .unwrap_or(false);
+
+        if suppress {
+            monitor_log(
+                LogLevel::Info,
+                "capture",
+                None,
+                "Skipped capture while a fullscreen app is focused",
+            );
+        }
+        suppress
     }
 
     /// 创建隐藏的消息窗口
@@ -674,8 +5353,119 @@ pub mod monitor {
         }
     }
 
-    /// 获取剪切板图片并保存到本地
-    pub fn get_clipboard_image(app_data_dir: &PathBuf) -> Result<String, String> {
+    /// 按 CF_HTML 头部的 StartFragment/EndFragment 字节偏移截出 HTML 片段，
+    /// 头部缺失或偏移非法时返回 None
+    fn cf_html_fragment(raw: &[u8]) -> Option<String> {
+        fn header_offset(header: &str, key: &str) -> Option<usize> {
+            let pos = header.find(key)?;
+            let rest = header[pos + key.len()..].strip_prefix(':')?;
+            let digits: String = rest.chars().take_while(|c| c.is_ascii_digit()).collect();
+            digits.parse().ok()
+        }
+
+        // 头部是 ASCII 键值行，取前 512 字节解析足够
+        let header = String::from_utf8_lossy(&raw[..raw.len().min(512)]).to_string();
+        let start = header_offset(&header, "StartFragment")?;
+        let end = header_offset(&header, "EndFragment")?;
+        if start >= end || end > raw.len() {
+            return None;
+        }
+        Some(String::from_utf8_lossy(&raw[start..end]).to_string())
+    }
+
+    /// 读取剪贴板上的富文本变体：优先注册格式 "HTML Format"（按 CF_HTML
+    /// 头部截取片段），其次 "Rich Text Format"。两者都没有时返回 None
+    pub fn get_clipboard_rich_text() -> Result<Option<(String, String)>, String> {
+        use windows_sys::Win32::System::DataExchange::RegisterClipboardFormatA;
+
+        unsafe {
+            let html_format = RegisterClipboardFormatA(b"HTML Format\0".as_ptr());
+            let rtf_format = RegisterClipboardFormatA(b"Rich Text Format\0".as_ptr());
+
+            if OpenClipboard(0 as HWND) == 0 {
+                return Err("Clipboard is busy or unavailable".to_string());
+            }
+
+            let mut result = None;
+            for (format, name) in [(html_format, "html"), (rtf_format, "rtf")] {
+                if format == 0 || IsClipboardFormatAvailable(format) == 0 {
+                    continue;
+                }
+                let h_data = GetClipboardData(format);
+                if h_data == 0 {
+                    continue;
+                }
+                let p_data = GlobalLock(h_data as *mut std::ffi::c_void);
+                if p_data.is_null() {
+                    continue;
+                }
+                let size = GlobalSize(h_data as *mut std::ffi::c_void);
+                let bytes = std::slice::from_raw_parts(p_data as *const u8, size).to_vec();
+                GlobalUnlock(h_data as *mut std::ffi::c_void);
+
+                // 数据末尾可能补 NUL，按第一个 NUL 截断
+                let len = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+                let content = if name == "html" {
+                    match cf_html_fragment(&bytes[..len]) {
+                        Some(fragment) => fragment,
+                        None => continue,
+                    }
+                } else {
+                    String::from_utf8_lossy(&bytes[..len]).to_string()
+                };
+
+                if !content.is_empty() {
+                    result = Some((content, name.to_string()));
+                    break;
+                }
+            }
+
+            CloseClipboard();
+            Ok(result)
+        }
+    }
+
+    /// 读取剪贴板里复制的文件路径列表（CF_HDROP）
+    pub fn get_clipboard_file_paths() -> Result<Vec<String>, String> {
+        use windows_sys::Win32::UI::Shell::{DragQueryFileW, HDROP};
+
+        unsafe {
+            if OpenClipboard(0 as HWND) == 0 {
+                return Err("Clipboard is busy or unavailable".to_string());
+            }
+
+            let h_drop = GetClipboardData(CF_HDROP);
+            if h_drop == 0 {
+                CloseClipboard();
+                return Err("Failed to get clipboard file list".to_string());
+            }
+
+            let h_drop = h_drop as HDROP;
+            let count = DragQueryFileW(h_drop, u32::MAX, ptr::null_mut(), 0);
+
+            let mut paths = Vec::with_capacity(count as usize);
+            for i in 0..count {
+                let len = DragQueryFileW(h_drop, i, ptr::null_mut(), 0);
+                if len == 0 {
+                    continue;
+                }
+
+                let mut buf = vec![0u16; len as usize + 1];
+                let copied = DragQueryFileW(h_drop, i, buf.as_mut_ptr(), buf.len() as u32);
+                if copied == 0 {
+                    continue;
+                }
+
+                paths.push(String::from_utf16_lossy(&buf[..copied as usize]));
+            }
+
+            CloseClipboard();
+            Ok(paths)
+        }
+    }
+
+    /// 获取剪切板图片并保存到本地，返回（文件路径, 像素内容哈希）
+    pub fn get_clipboard_image(app_data_dir: &PathBuf) -> Result<(String, String), String> {
         unsafe {
             // 尝试打开剪贴板，如果失败（可能被其他程序占用），立即返回错误
             // 不重试，避免阻塞用户的复制操作
@@ -757,16 +5547,26 @@ pub mod monitor {
                 if file_path.exists() {
                     GlobalUnlock(h_data as *mut std::ffi::c_void);
                     CloseClipboard();
-                    return Ok(file_path.to_string_lossy().to_string());
+                    return Ok((file_path.to_string_lossy().to_string(), hash_str));
                 }
 
+                // DIB 携带的物理分辨率（像素/米），写入 PNG 的 pHYs 块以保留 DPI
+                let x_ppm = (*bmi).biXPelsPerMeter;
+                let y_ppm = (*bmi).biYPelsPerMeter;
+                let pixel_dims = if x_ppm > 0 && y_ppm > 0 {
+                    Some((x_ppm as u32, y_ppm as u32))
+                } else {
+                    None
+                };
+
                 // 保存为 PNG
-                let save_result = save_png(&file_path, &rgba_data, width as u32, height as u32);
+                let save_result =
+                    save_png(&file_path, &rgba_data, width as u32, height as u32, pixel_dims);
 
                 GlobalUnlock(h_data as *mut std::ffi::c_void);
                 
                 match save_result {
-                    Ok(_) => Ok(file_path.to_string_lossy().to_string()),
+                    Ok(_) => Ok((file_path.to_string_lossy().to_string(), hash_str)),
                     Err(e) => Err(format!("Failed to save PNG: {}", e)),
                 }
             } else {
@@ -778,11 +5578,231 @@ pub mod monitor {
         }
     }
 
-    /// 保存图片为 PNG 格式
-    fn save_png(path: &std::path::Path, data: &[u8], width: u32, height: u32) -> Result<(), String> {
+    /// 把已存储的 PNG 图片重编码为 DIB 写回系统剪贴板，
+    /// 并同步监控去重状态，避免刚写回的图片立即被再次入库
+    pub fn set_clipboard_image(path: &PathBuf) -> Result<(), String> {
+        use windows_sys::Win32::System::Memory::{
+            GlobalAlloc, GlobalLock, GlobalUnlock, GMEM_MOVEABLE,
+        };
+
+        let image_data =
+            std::fs::read(path).map_err(|e| format!("Failed to read image: {}", e))?;
+
+        let decoder = png::Decoder::new(&image_data[..]);
+        let mut reader = decoder
+            .read_info()
+            .map_err(|e| format!("Failed to decode PNG: {}", e))?;
+        let mut buf = vec![0u8; reader.output_buffer_size()];
+        let info = reader
+            .next_frame(&mut buf)
+            .map_err(|e| format!("Failed to read PNG frame: {}", e))?;
+
+        let width = info.width as usize;
+        let height = info.height as usize;
+
+        // 统一成 RGBA（行自顶向下）
+        let rgba: Vec<u8> = match info.color_type {
+            png::ColorType::Rgba => buf[..info.buffer_size()].to_vec(),
+            png::ColorType::Rgb => {
+                let mut out = Vec::with_capacity(width * height * 4);
+                for px in buf[..info.buffer_size()].chunks_exact(3) {
+                    out.extend_from_slice(&[px[0], px[1], px[2], 255]);
+                }
+                out
+            }
+            other => return Err(format!("Unsupported PNG color type: {:?}", other)),
+        };
+
+        unsafe {
+            if OpenClipboard(0 as HWND) == 0 {
+                return Err("Failed to open clipboard".to_string());
+            }
+
+            EmptyClipboard();
+
+            // 32 位 BI_RGB 的 DIB：头 + 自底向上的 BGRA 行
+            let header_size = std::mem::size_of::<BITMAPINFOHEADER>();
+            let image_size = width * height * 4;
+            let total_size = header_size + image_size;
+
+            let h_mem = GlobalAlloc(GMEM_MOVEABLE, total_size);
+            if h_mem.is_null() {
+                CloseClipboard();
+                return Err("Failed to allocate memory".to_string());
+            }
+
+            let p_mem = GlobalLock(h_mem);
+            if p_mem.is_null() {
+                CloseClipboard();
+                return Err("Failed to lock memory".to_string());
+            }
+
+            let bmi = p_mem as *mut BITMAPINFOHEADER;
+            (*bmi).biSize = header_size as u32;
+            (*bmi).biWidth = width as i32;
+            (*bmi).biHeight = height as i32;
+            (*bmi).biPlanes = 1;
+            (*bmi).biBitCount = 32;
+            (*bmi).biCompression = 0; // BI_RGB
+            (*bmi).biSizeImage = image_size as u32;
+            (*bmi).biXPelsPerMeter = 0;
+            (*bmi).biYPelsPerMeter = 0;
+            (*bmi).biClrUsed = 0;
+            (*bmi).biClrImportant = 0;
+
+            let image_data_ptr = (p_mem as *mut u8).add(header_size);
+            for y in 0..height {
+                for x in 0..width {
+                    let src = ((height - 1 - y) * width + x) * 4;
+                    let dst = (y * width + x) * 4;
+                    *image_data_ptr.add(dst) = rgba[src + 2]; // B
+                    *image_data_ptr.add(dst + 1) = rgba[src + 1]; // G
+                    *image_data_ptr.add(dst + 2) = rgba[src]; // R
+                    *image_data_ptr.add(dst + 3) = rgba[src + 3]; // A
+                }
+            }
+
+            GlobalUnlock(h_mem);
+
+            if SetClipboardData(CF_DIB, h_mem as isize) == 0 {
+                CloseClipboard();
+                return Err("Failed to set clipboard data".to_string());
+            }
+
+            CloseClipboard();
+        }
+
+        // 按捕获路径同样的像素流（alpha 固定 255）计算哈希，同步去重状态
+        let mut hash_input = rgba;
+        for px in hash_input.chunks_exact_mut(4) {
+            px[3] = 255;
+        }
+        let mut hasher = Sha256::new();
+        hasher.update(&hash_input);
+        set_monitor_dedup_last_image(&format!("{:x}", hasher.finalize()));
+
+        Ok(())
+    }
+
+    /// 截取屏幕指定区域并直接入库为图片项，不经过系统剪贴板
+    pub fn capture_screen_region(
+        app_data_dir: &PathBuf,
+        x: i32,
+        y: i32,
+        width: u32,
+        height: u32,
+    ) -> Result<ClipboardItem, String> {
+        if width == 0 || height == 0 {
+            return Err("Invalid capture region".to_string());
+        }
+
+        let rgba_data = grab_region(x, y, width, height)?;
+
+        let clipboard_images_dir = app_data_dir.join("clipboard_images");
+        std::fs::create_dir_all(&clipboard_images_dir)
+            .map_err(|e| format!("Failed to create clipboard images directory: {}", e))?;
+
+        // 与剪贴板图片一致：内容哈希做文件名，天然去重
+        let mut hasher = Sha256::new();
+        hasher.update(&rgba_data);
+        let hash_str = format!("{:x}", hasher.finalize());
+        let filename = format!("clipboard_{}.png", &hash_str[..16]);
+        let file_path = clipboard_images_dir.join(&filename);
+
+        if !file_path.exists() {
+            save_png(&file_path, &rgba_data, width, height, None)?;
+        }
+
+        add_clipboard_item(
+            file_path.to_string_lossy().to_string(),
+            "image".to_string(),
+            app_data_dir,
+        )
+    }
+
+    /// 用 GDI 抓取屏幕区域，返回 RGBA 像素
+    fn grab_region(x: i32, y: i32, width: u32, height: u32) -> Result<Vec<u8>, String> {
+        use windows_sys::Win32::Graphics::Gdi::{
+            BitBlt, CreateCompatibleBitmap, CreateCompatibleDC, DeleteDC, DeleteObject, GetDC,
+            ReleaseDC, SelectObject, SRCCOPY,
+        };
+
+        unsafe {
+            let screen_dc = GetDC(0 as HWND);
+            if screen_dc == 0 {
+                return Err("Failed to get screen DC".to_string());
+            }
+
+            let mem_dc = CreateCompatibleDC(screen_dc);
+            let bitmap = CreateCompatibleBitmap(screen_dc, width as i32, height as i32);
+            let old_obj = SelectObject(mem_dc, bitmap);
+
+            let blt_ok = BitBlt(
+                mem_dc,
+                0,
+                0,
+                width as i32,
+                height as i32,
+                screen_dc,
+                x,
+                y,
+                SRCCOPY,
+            );
+
+            let mut result = Err("Failed to copy screen region".to_string());
+            if blt_ok != 0 {
+                let mut bmi: BITMAPINFO = std::mem::zeroed();
+                bmi.bmiHeader.biSize = std::mem::size_of::<BITMAPINFOHEADER>() as u32;
+                bmi.bmiHeader.biWidth = width as i32;
+                // 负高度表示自顶向下的像素顺序，省去翻转
+                bmi.bmiHeader.biHeight = -(height as i32);
+                bmi.bmiHeader.biPlanes = 1;
+                bmi.bmiHeader.biBitCount = 32;
+                bmi.bmiHeader.biCompression = BI_RGB;
+
+                let mut buf = vec![0u8; width as usize * height as usize * 4];
+                let lines = GetDIBits(
+                    mem_dc,
+                    bitmap,
+                    0,
+                    height,
+                    buf.as_mut_ptr() as *mut std::ffi::c_void,
+                    &mut bmi,
+                    DIB_RGB_COLORS,
+                );
+
+                if lines == height as i32 {
+                    // GDI 输出 BGRA，转成 RGBA 并填满 alpha
+                    for px in buf.chunks_exact_mut(4) {
+                        px.swap(0, 2);
+                        px[3] = 255;
+                    }
+                    result = Ok(buf);
+                } else {
+                    result = Err("Failed to read captured pixels".to_string());
+                }
+            }
+
+            SelectObject(mem_dc, old_obj);
+            DeleteObject(bitmap);
+            DeleteDC(mem_dc);
+            ReleaseDC(0 as HWND, screen_dc);
+
+            result
+        }
+    }
+
+    /// 保存图片为 PNG 格式，pixel_dims 为 (横向, 纵向) 像素/米
+    fn save_png(
+        path: &std::path::Path,
+        data: &[u8],
+        width: u32,
+        height: u32,
+        pixel_dims: Option<(u32, u32)>,
+    ) -> Result<(), String> {
         use std::fs::File;
         use std::io::BufWriter;
-        
+
         let file = File::create(path)
             .map_err(|e| format!("Failed to create file: {}", e))?;
         let writer = BufWriter::new(file);
@@ -790,6 +5810,13 @@ pub mod monitor {
         let mut encoder = png::Encoder::new(writer, width, height);
         encoder.set_color(png::ColorType::Rgba);
         encoder.set_depth(png::BitDepth::Eight);
+        if let Some((xppu, yppu)) = pixel_dims {
+            encoder.set_pixel_dims(Some(png::PixelDimensions {
+                xppu,
+                yppu,
+                unit: png::Unit::Meter,
+            }));
+        }
 
         let mut writer = encoder.write_header()
             .map_err(|e| format!("Failed to write PNG header: {}", e))?;
@@ -800,3 +5827,155 @@ pub mod monitor {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fts_match_expr_quotes_and_joins_terms() {
+        assert_eq!(fts_match_expr("  "), None);
+        assert_eq!(fts_match_expr("hello"), Some("\"hello\"*".to_string()));
+        assert_eq!(
+            fts_match_expr("foo bar"),
+            Some("\"foo\"* AND \"bar\"*".to_string())
+        );
+        assert_eq!(
+            fts_match_expr("say \"hi\""),
+            Some("\"say\"* AND \"\"\"hi\"\"\"*".to_string())
+        );
+    }
+
+    #[test]
+    fn test_source_matches_path_and_basename() {
+        let stored = r"C:\Program Files\App\app.exe";
+        assert!(source_matches(stored, r"C:\Program Files\App\app.exe"));
+        assert!(source_matches(stored, "app.exe"));
+        assert!(source_matches(stored, "App"));
+        assert!(!source_matches(stored, "other"));
+    }
+
+    #[test]
+    fn test_detect_text_kind_classifies_common_shapes() {
+        assert_eq!(detect_text_kind("https://example.com/page"), "url");
+        assert_eq!(detect_text_kind("www.example.com"), "url");
+        assert_eq!(detect_text_kind("user@example.com"), "email");
+        assert_eq!(detect_text_kind("#ff8800"), "hex_color");
+        assert_eq!(detect_text_kind("#f80"), "hex_color");
+        assert_eq!(detect_text_kind("#xyz"), "plain");
+        assert_eq!(
+            detect_text_kind("fn main() {\n    let x = 1;\n    println!(\"{}\", x);\n}"),
+            "code"
+        );
+        assert_eq!(detect_text_kind("just a sentence"), "plain");
+        assert_eq!(detect_text_kind("not @ an email"), "plain");
+    }
+
+    #[test]
+    fn test_nfc_normalize_merges_equivalent_forms() {
+        // "é" 的组合形式（NFD）归一化成预组合形式（NFC）
+        assert_eq!(nfc_normalize("e\u{301}"), "\u{e9}");
+        assert_eq!(nfc_normalize("plain"), "plain");
+    }
+
+    #[test]
+    fn test_fingerprint_ignores_volatile_parts() {
+        let patterns = vec![r"\d{2}:\d{2}:\d{2}".to_string()];
+        assert_eq!(
+            fingerprint("12:00:01 job done", &patterns),
+            fingerprint("13:45:59 job done", &patterns)
+        );
+        assert_ne!(
+            fingerprint("12:00:01 job done", &patterns),
+            fingerprint("12:00:01 job failed", &patterns)
+        );
+        // 非法正则被跳过，退化为普通哈希
+        let bad = vec!["(".to_string()];
+        assert_eq!(fingerprint("same", &bad), fingerprint("same", &bad));
+    }
+
+    #[test]
+    fn test_extract_links_dedup_and_trailing_punctuation() {
+        let text = "see https://example.com/a, then http://b.io. again https://example.com/a";
+        assert_eq!(
+            extract_links_from_text(text),
+            vec!["https://example.com/a".to_string(), "http://b.io".to_string()]
+        );
+        assert!(extract_links_from_text("no links here, httpx://nope").is_empty());
+    }
+
+    #[test]
+    fn test_format_rank_orders_and_defaults() {
+        let priority = vec!["image".to_string(), "text".to_string()];
+        assert!(format_rank(&priority, "image") < format_rank(&priority, "text"));
+        assert_eq!(format_rank(&priority, "rtf"), priority.len());
+    }
+
+    #[test]
+    fn test_compute_preview_collapses_and_truncates() {
+        assert_eq!(compute_preview("a\nb\n  c", 120, true), "a b c");
+        assert_eq!(compute_preview("a\nb", 120, false), "a\nb");
+        assert_eq!(compute_preview("hello world", 5, true), "hello…");
+    }
+
+    #[test]
+    fn test_new_item_id_unique_and_typed() {
+        let a = new_item_id("text");
+        let b = new_item_id("text");
+        assert_ne!(a, b);
+        assert!(a.starts_with("text-"));
+        assert!(new_item_id("image").starts_with("image-"));
+    }
+
+    #[test]
+    fn test_normalize_text_newlines() {
+        assert_eq!(normalize_text("a\r\nb"), "a\nb");
+        assert_eq!(normalize_text("a\rb"), "a\nb");
+        assert_eq!(normalize_text("a\nb"), "a\nb");
+    }
+
+    #[test]
+    fn test_normalize_text_trims_whitespace() {
+        assert_eq!(normalize_text("  hello  "), "hello");
+        assert_eq!(normalize_text("code\n\n"), "code");
+        assert_eq!(normalize_text("\t indented \n"), "indented");
+    }
+
+    #[test]
+    fn test_compute_title_text_first_line() {
+        assert_eq!(compute_title("first line\nsecond line", "text"), "first line");
+        assert_eq!(compute_title("\n\n  indented  \nmore", "text"), "indented");
+        let long = "x".repeat(200);
+        assert_eq!(compute_title(&long, "text").chars().count(), TITLE_MAX_CHARS);
+    }
+
+    #[test]
+    fn test_compute_title_file_name() {
+        assert_eq!(compute_title("C:/dir/report.pdf", "file"), "report.pdf");
+        assert_eq!(
+            compute_title("C:/a/first.txt\nC:/b/second.txt", "file"),
+            "first.txt"
+        );
+    }
+
+    #[test]
+    fn test_detect_table_tab_separated() {
+        assert_eq!(detect_table("a\tb\tc\n1\t2\t3"), Some((2, 3)));
+        assert_eq!(detect_table("x\ty\n1\t2\n3\t4"), Some((3, 2)));
+    }
+
+    #[test]
+    fn test_detect_table_rejects_prose() {
+        // 单行不算表格
+        assert_eq!(detect_table("a\tb\tc"), None);
+        // 列数不一致不算表格
+        assert_eq!(detect_table("a\tb\n1\t2\t3"), None);
+        // 普通多行文本不算表格
+        assert_eq!(detect_table("hello world\nsecond line"), None);
+    }
+
+    #[test]
+    fn test_detect_table_comma_separated() {
+        assert_eq!(detect_table("name,age\nalice,30\nbob,25"), Some((3, 2)));
+    }
+}
@@ -5458,6 +5458,12 @@ pub fn get_everything_custom_filters(app: tauri::AppHandle) -> Result<Vec<everyt
     everything_filters::load_custom_filters(&app_data_dir)
 }
 
+#[tauri::command]
+pub fn migrate_everything_custom_filters(app: tauri::AppHandle) -> Result<u32, String> {
+    let app_data_dir = get_app_data_dir(&app)?;
+    everything_filters::migrate_filters(&app_data_dir)
+}
+
 #[tauri::command]
 pub fn save_everything_custom_filters(
     app: tauri::AppHandle,
@@ -5467,6 +5473,41 @@ pub fn save_everything_custom_filters(
     everything_filters::save_custom_filters(&app_data_dir, &filters)
 }
 
+#[tauri::command]
+pub fn export_everything_custom_filters(
+    app: tauri::AppHandle,
+    out_path: String,
+) -> Result<u32, String> {
+    let app_data_dir = get_app_data_dir(&app)?;
+    everything_filters::export_custom_filters(&app_data_dir, std::path::Path::new(&out_path))
+}
+
+#[tauri::command]
+pub fn import_everything_custom_filters(
+    app: tauri::AppHandle,
+    in_path: String,
+    replace: bool,
+) -> Result<Vec<everything_filters::CustomFilter>, String> {
+    let app_data_dir = get_app_data_dir(&app)?;
+    everything_filters::import_custom_filters(&app_data_dir, std::path::Path::new(&in_path), replace)
+}
+
+#[tauri::command]
+pub fn get_everything_filters_version(app: tauri::AppHandle) -> Result<u64, String> {
+    let app_data_dir = get_app_data_dir(&app)?;
+    everything_filters::get_filters_version(&app_data_dir)
+}
+
+#[tauri::command]
+pub fn replace_everything_custom_filters(
+    app: tauri::AppHandle,
+    expected_version: u64,
+    filters: Vec<everything_filters::CustomFilter>,
+) -> Result<u64, String> {
+    let app_data_dir = get_app_data_dir(&app)?;
+    everything_filters::replace_filters(&app_data_dir, expected_version, &filters)
+}
+
 #[tauri::command]
 pub fn get_hotkey_config(app: tauri::AppHandle) -> Result<Option<settings::HotkeyConfig>, String> {
     let app_data_dir = get_app_data_dir(&app)?;
@@ -6371,6 +6412,27 @@ pub async fn add_clipboard_item(
     crate::clipboard::add_clipboard_item(content, content_type, &app_data_dir)
 }
 
+#[tauri::command]
+pub async fn add_clipboard_item_force(
+    content: String,
+    content_type: String,
+    app_handle: tauri::AppHandle,
+) -> Result<crate::clipboard::ClipboardItem, String> {
+    let app_data_dir = get_app_data_dir(&app_handle)?;
+    crate::clipboard::add_clipboard_item_force(content, content_type, &app_data_dir)
+}
+
+#[tauri::command]
+pub async fn add_clipboard_item_at(
+    content: String,
+    content_type: String,
+    created_at: u64,
+    app_handle: tauri::AppHandle,
+) -> Result<crate::clipboard::ClipboardItem, String> {
+    let app_data_dir = get_app_data_dir(&app_handle)?;
+    crate::clipboard::add_clipboard_item_at(content, content_type, created_at, &app_data_dir)
+}
+
 #[tauri::command]
 pub async fn update_clipboard_item(
     id: String,
@@ -6396,7 +6458,30 @@ pub async fn delete_clipboard_item(
     app_handle: tauri::AppHandle,
 ) -> Result<(), String> {
     let app_data_dir = get_app_data_dir(&app_handle)?;
-    crate::clipboard::delete_clipboard_item(id, &app_data_dir)
+    Ok(crate::clipboard::delete_clipboard_item(id, &app_data_dir)?)
+}
+
+#[tauri::command]
+pub async fn delete_clipboard_items(
+    ids: Vec<String>,
+    app_handle: tauri::AppHandle,
+) -> Result<u32, String> {
+    let app_data_dir = get_app_data_dir(&app_handle)?;
+    Ok(crate::clipboard::delete_clipboard_items(ids, &app_data_dir)?)
+}
+
+#[tauri::command]
+pub async fn set_favorite_clipboard_items(
+    ids: Vec<String>,
+    favorite: bool,
+    app_handle: tauri::AppHandle,
+) -> Result<u32, String> {
+    let app_data_dir = get_app_data_dir(&app_handle)?;
+    Ok(crate::clipboard::set_favorite_clipboard_items(
+        ids,
+        favorite,
+        &app_data_dir,
+    )?)
 }
 
 #[tauri::command]
@@ -6408,10 +6493,469 @@ pub async fn clear_clipboard_history(app_handle: tauri::AppHandle) -> Result<(),
 #[tauri::command]
 pub async fn search_clipboard_items(
     query: String,
+    source_app: Option<String>,
+    kind: Option<String>,
+    app_handle: tauri::AppHandle,
+) -> Result<Vec<crate::clipboard::ClipboardItem>, String> {
+    let app_data_dir = get_app_data_dir(&app_handle)?;
+    crate::clipboard::search_clipboard_items(
+        &query,
+        source_app.as_deref(),
+        kind.as_deref(),
+        &app_data_dir,
+    )
+}
+
+#[tauri::command]
+pub async fn save_clipboard_item_as_snippet(
+    id: String,
+    label: String,
+    app_handle: tauri::AppHandle,
+) -> Result<crate::clipboard::Snippet, String> {
+    let app_data_dir = get_app_data_dir(&app_handle)?;
+    crate::clipboard::save_as_snippet(&id, &label, &app_data_dir)
+}
+
+#[tauri::command]
+pub async fn get_clipboard_snippets(
+    app_handle: tauri::AppHandle,
+) -> Result<Vec<crate::clipboard::Snippet>, String> {
+    let app_data_dir = get_app_data_dir(&app_handle)?;
+    crate::clipboard::get_snippets(&app_data_dir)
+}
+
+#[tauri::command]
+pub async fn delete_clipboard_snippet(
+    id: String,
+    app_handle: tauri::AppHandle,
+) -> Result<(), String> {
+    let app_data_dir = get_app_data_dir(&app_handle)?;
+    crate::clipboard::delete_snippet(&id, &app_data_dir)
+}
+
+#[tauri::command]
+pub async fn get_clipboard_items_offset(
+    offset: u32,
+    limit: u32,
+    content_type_filter: Option<String>,
+    app_handle: tauri::AppHandle,
+) -> Result<crate::clipboard::ClipboardOffsetPage, String> {
+    let app_data_dir = get_app_data_dir(&app_handle)?;
+    crate::clipboard::get_clipboard_items_offset(&app_data_dir, offset, limit, content_type_filter)
+}
+
+#[tauri::command]
+pub async fn get_clipboard_storage_usage(
+    app_handle: tauri::AppHandle,
+) -> Result<crate::clipboard::StorageUsage, String> {
+    let app_data_dir = get_app_data_dir(&app_handle)?;
+    crate::clipboard::get_storage_usage(&app_data_dir)
+}
+
+#[tauri::command]
+pub async fn enforce_clipboard_total_budget(
+    app_handle: tauri::AppHandle,
+) -> Result<Vec<String>, String> {
+    let app_data_dir = get_app_data_dir(&app_handle)?;
+    crate::clipboard::enforce_total_budget(&app_data_dir)
+}
+
+#[tauri::command]
+pub async fn export_clipboard_history(
+    out_path: String,
+    app_handle: tauri::AppHandle,
+) -> Result<u32, String> {
+    let app_data_dir = get_app_data_dir(&app_handle)?;
+    crate::clipboard::export_clipboard_history(&app_data_dir, std::path::Path::new(&out_path))
+}
+
+#[tauri::command]
+pub async fn import_clipboard_history(
+    in_path: String,
+    merge: bool,
+    app_handle: tauri::AppHandle,
+) -> Result<u32, String> {
+    let app_data_dir = get_app_data_dir(&app_handle)?;
+    crate::clipboard::import_clipboard_history(&app_data_dir, std::path::Path::new(&in_path), merge)
+}
+
+#[tauri::command]
+pub async fn enable_clipboard_encryption(
+    passphrase: String,
+    app_handle: tauri::AppHandle,
+) -> Result<(), String> {
+    let app_data_dir = get_app_data_dir(&app_handle)?;
+    crate::clipboard::enable_clipboard_encryption(&passphrase, &app_data_dir)
+}
+
+#[tauri::command]
+pub async fn disable_clipboard_encryption(
+    passphrase: String,
+    app_handle: tauri::AppHandle,
+) -> Result<(), String> {
+    let app_data_dir = get_app_data_dir(&app_handle)?;
+    crate::clipboard::disable_clipboard_encryption(&passphrase, &app_data_dir)
+}
+
+#[tauri::command]
+pub async fn unlock_clipboard_encryption(
+    passphrase: String,
+    app_handle: tauri::AppHandle,
+) -> Result<(), String> {
+    let app_data_dir = get_app_data_dir(&app_handle)?;
+    crate::clipboard::unlock_clipboard_encryption(&passphrase, &app_data_dir)
+}
+
+#[tauri::command]
+pub async fn lock_clipboard_encryption() -> Result<(), String> {
+    crate::clipboard::lock_clipboard_encryption();
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_clipboard_encryption_status(
+    app_handle: tauri::AppHandle,
+) -> Result<crate::clipboard::ClipboardEncryptionStatus, String> {
+    let app_data_dir = get_app_data_dir(&app_handle)?;
+    crate::clipboard::get_clipboard_encryption_status(&app_data_dir)
+}
+
+#[tauri::command]
+pub async fn get_db_info(app_handle: tauri::AppHandle) -> Result<crate::db::DbInfo, String> {
+    let app_data_dir = get_app_data_dir(&app_handle)?;
+    crate::db::get_db_info(&app_data_dir)
+}
+
+#[tauri::command]
+pub async fn get_clipboard_monitor_dedup_state(
+) -> Result<crate::clipboard::MonitorDedupState, String> {
+    Ok(crate::clipboard::get_monitor_dedup_state())
+}
+
+#[tauri::command]
+pub async fn reset_clipboard_monitor_dedup_state() -> Result<(), String> {
+    crate::clipboard::reset_monitor_dedup_state();
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn snapshot_clipboard_ids(
+    app_handle: tauri::AppHandle,
+) -> Result<Vec<String>, String> {
+    let app_data_dir = get_app_data_dir(&app_handle)?;
+    crate::clipboard::snapshot_ids(&app_data_dir)
+}
+
+#[tauri::command]
+pub async fn diff_clipboard_since(
+    snapshot: Vec<String>,
+    app_handle: tauri::AppHandle,
+) -> Result<crate::clipboard::HistoryDiff, String> {
+    let app_data_dir = get_app_data_dir(&app_handle)?;
+    crate::clipboard::diff_since(&snapshot, &app_data_dir)
+}
+
+#[tauri::command]
+pub async fn get_clipboard_monitor_status() -> Result<Option<String>, String> {
+    Ok(crate::clipboard::get_monitor_status())
+}
+
+#[tauri::command]
+pub async fn pause_clipboard_monitor() -> Result<(), String> {
+    crate::clipboard::pause_clipboard_monitor();
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn resume_clipboard_monitor() -> Result<(), String> {
+    crate::clipboard::resume_clipboard_monitor();
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_clipboard_monitor_paused() -> Result<bool, String> {
+    Ok(crate::clipboard::is_clipboard_monitor_paused())
+}
+
+#[tauri::command]
+pub async fn set_clipboard_poll_interval(ms: u64) -> Result<(), String> {
+    crate::clipboard::set_clipboard_poll_interval(ms);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn copy_latest_clipboard_of_type(
+    content_type: String,
+    app_handle: tauri::AppHandle,
+) -> Result<Option<crate::clipboard::ClipboardItem>, String> {
+    let app_data_dir = get_app_data_dir(&app_handle)?;
+    let item = crate::clipboard::latest_of_type(&content_type, &app_data_dir)?;
+
+    if let Some(item) = &item {
+        match item.content_type.as_str() {
+            "image" => copy_image_to_clipboard(item.content.clone()).await?,
+            // 文件/文本类都按文本回写，文件项的内容本身就是路径
+            _ => crate::clipboard::set_clipboard_text(
+                item.raw_content.as_deref().unwrap_or(&item.content),
+            )?,
+        }
+    }
+
+    Ok(item)
+}
+
+#[tauri::command]
+pub async fn extract_clipboard_item_links(
+    id: String,
+    app_handle: tauri::AppHandle,
+) -> Result<Vec<String>, String> {
+    let app_data_dir = get_app_data_dir(&app_handle)?;
+    crate::clipboard::extract_links(&id, &app_data_dir)
+}
+
+#[tauri::command]
+pub async fn rebuild_clipboard_fts_index(app_handle: tauri::AppHandle) -> Result<(), String> {
+    let app_data_dir = get_app_data_dir(&app_handle)?;
+    crate::clipboard::rebuild_fts_index(&app_data_dir)
+}
+
+#[tauri::command]
+pub async fn check_clipboard_fts_consistency(
+    app_handle: tauri::AppHandle,
+) -> Result<crate::clipboard::FtsConsistency, String> {
+    let app_data_dir = get_app_data_dir(&app_handle)?;
+    crate::clipboard::check_fts_consistency(&app_data_dir)
+}
+
+#[tauri::command]
+pub async fn get_clipboard_session_items(
+    app_handle: tauri::AppHandle,
+) -> Result<Vec<crate::clipboard::ClipboardItem>, String> {
+    let app_data_dir = get_app_data_dir(&app_handle)?;
+    crate::clipboard::get_session_items(&app_data_dir)
+}
+
+#[tauri::command]
+pub async fn reset_clipboard_session(app_handle: tauri::AppHandle) -> Result<u64, String> {
+    let app_data_dir = get_app_data_dir(&app_handle)?;
+    crate::clipboard::reset_session(&app_data_dir)
+}
+
+#[tauri::command]
+pub async fn capture_screen_region(
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+    app_handle: tauri::AppHandle,
+) -> Result<crate::clipboard::ClipboardItem, String> {
+    let app_data_dir = get_app_data_dir(&app_handle)?;
+
+    #[cfg(target_os = "windows")]
+    {
+        crate::clipboard::monitor::capture_screen_region(&app_data_dir, x, y, width, height)
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    {
+        let _ = (x, y, width, height, app_data_dir);
+        Err("Screen region capture is only supported on Windows".to_string())
+    }
+}
+
+#[tauri::command]
+pub async fn move_clipboard_items_to_profile(
+    ids: Vec<String>,
+    target_profile: String,
+    app_handle: tauri::AppHandle,
+) -> Result<u32, String> {
+    let app_data_dir = get_app_data_dir(&app_handle)?;
+    crate::clipboard::move_items_to_profile(ids, &target_profile, &app_data_dir)
+}
+
+#[tauri::command]
+pub async fn get_clipboard_growth_stats(
+    window_days: Option<u32>,
+    app_handle: tauri::AppHandle,
+) -> Result<crate::clipboard::GrowthStats, String> {
+    let app_data_dir = get_app_data_dir(&app_handle)?;
+    crate::clipboard::get_growth_stats(&app_data_dir, window_days.unwrap_or(30))
+}
+
+#[tauri::command]
+pub async fn find_clipboard_item_by_hash(
+    hash: String,
+    app_handle: tauri::AppHandle,
+) -> Result<Option<crate::clipboard::ClipboardItem>, String> {
+    let app_data_dir = get_app_data_dir(&app_handle)?;
+    crate::clipboard::find_by_content_hash(&hash, &app_data_dir)
+}
+
+#[tauri::command]
+pub async fn toggle_favorite_and_list(
+    id: String,
+    limit: Option<u32>,
+    app_handle: tauri::AppHandle,
+) -> Result<crate::clipboard::ToggleFavoriteResult, String> {
+    let app_data_dir = get_app_data_dir(&app_handle)?;
+    crate::clipboard::toggle_favorite_and_list(id, limit.unwrap_or(50), &app_data_dir)
+}
+
+#[tauri::command]
+pub async fn audit_clipboard_content_types(
     app_handle: tauri::AppHandle,
 ) -> Result<Vec<crate::clipboard::ClipboardItem>, String> {
     let app_data_dir = get_app_data_dir(&app_handle)?;
-    crate::clipboard::search_clipboard_items(&query, &app_data_dir)
+    crate::clipboard::audit_content_types(&app_data_dir)
+}
+
+#[tauri::command]
+pub async fn copy_clipboard_items_combined(
+    ids: Vec<String>,
+    separator: String,
+    app_handle: tauri::AppHandle,
+) -> Result<u32, String> {
+    let app_data_dir = get_app_data_dir(&app_handle)?;
+    crate::clipboard::copy_items_to_clipboard(ids, &separator, &app_data_dir)
+}
+
+#[tauri::command]
+pub async fn reencode_clipboard_images(
+    target_format: String,
+    quality: u8,
+    app_handle: tauri::AppHandle,
+) -> Result<crate::clipboard::ReencodeReport, String> {
+    let app_data_dir = get_app_data_dir(&app_handle)?;
+    crate::clipboard::reencode_images(&app_data_dir, &target_format, quality)
+}
+
+#[tauri::command]
+pub async fn set_clipboard_item_tags(
+    id: String,
+    tags: Vec<String>,
+    app_handle: tauri::AppHandle,
+) -> Result<crate::clipboard::ClipboardItem, String> {
+    let app_data_dir = get_app_data_dir(&app_handle)?;
+    crate::clipboard::set_item_tags(id, tags, &app_data_dir)
+}
+
+#[tauri::command]
+pub async fn list_clipboard_tags(
+    app_handle: tauri::AppHandle,
+) -> Result<Vec<(String, u64)>, String> {
+    let app_data_dir = get_app_data_dir(&app_handle)?;
+    crate::clipboard::list_tags(&app_data_dir)
+}
+
+#[tauri::command]
+pub async fn add_clipboard_file_paths(
+    paths: Vec<String>,
+    app_handle: tauri::AppHandle,
+) -> Result<Vec<crate::clipboard::ClipboardItem>, String> {
+    let app_data_dir = get_app_data_dir(&app_handle)?;
+    crate::clipboard::add_clipboard_file_paths(paths, &app_data_dir)
+}
+
+#[tauri::command]
+pub async fn verify_clipboard_no_duplicates(
+    app_handle: tauri::AppHandle,
+) -> Result<Vec<(String, String)>, String> {
+    let app_data_dir = get_app_data_dir(&app_handle)?;
+    crate::clipboard::verify_no_duplicates(&app_data_dir)
+}
+
+#[tauri::command]
+pub async fn resolve_clipboard_duplicates(
+    app_handle: tauri::AppHandle,
+) -> Result<crate::clipboard::DuplicateReport, String> {
+    let app_data_dir = get_app_data_dir(&app_handle)?;
+    crate::clipboard::resolve_duplicates(&app_data_dir)
+}
+
+#[tauri::command]
+pub async fn export_clipboard_filtered(
+    out_path: String,
+    filter: crate::clipboard::ExportFilter,
+    app_handle: tauri::AppHandle,
+) -> Result<u32, String> {
+    let app_data_dir = get_app_data_dir(&app_handle)?;
+    crate::clipboard::export_filtered(&app_data_dir, &out_path, &filter)
+}
+
+#[tauri::command]
+pub async fn collapse_clipboard_cross_type_duplicates(
+    app_handle: tauri::AppHandle,
+) -> Result<crate::clipboard::CollapseReport, String> {
+    let app_data_dir = get_app_data_dir(&app_handle)?;
+    crate::clipboard::collapse_cross_type_duplicates(&app_data_dir)
+}
+
+#[tauri::command]
+pub async fn clipboard_content_matches_blocklist(
+    content: String,
+    app_handle: tauri::AppHandle,
+) -> Result<bool, String> {
+    let app_data_dir = get_app_data_dir(&app_handle)?;
+    crate::clipboard::content_matches_blocklist(&content, &app_data_dir)
+}
+
+#[tauri::command]
+pub async fn add_clipboard_content_to_blocklist(
+    content: String,
+    app_handle: tauri::AppHandle,
+) -> Result<(), String> {
+    let app_data_dir = get_app_data_dir(&app_handle)?;
+    crate::clipboard::add_to_blocklist(&content, &app_data_dir)
+}
+
+#[tauri::command]
+pub async fn compute_clipboard_dominant_color(
+    id: String,
+    app_handle: tauri::AppHandle,
+) -> Result<String, String> {
+    let app_data_dir = get_app_data_dir(&app_handle)?;
+    crate::clipboard::compute_dominant_color(id, &app_data_dir)
+}
+
+#[tauri::command]
+pub async fn import_clipboard_text_files(
+    dir: String,
+    recursive: bool,
+    app_handle: tauri::AppHandle,
+) -> Result<crate::clipboard::ImportReport, String> {
+    let app_data_dir = get_app_data_dir(&app_handle)?;
+    crate::clipboard::import_text_files(&dir, &app_data_dir, recursive)
+}
+
+#[tauri::command]
+pub async fn delete_clipboard_items_by_source(
+    source_app: String,
+    include_favorites: bool,
+    app_handle: tauri::AppHandle,
+) -> Result<u32, String> {
+    let app_data_dir = get_app_data_dir(&app_handle)?;
+    crate::clipboard::delete_items_by_source(&source_app, &app_data_dir, include_favorites)
+}
+
+#[tauri::command]
+pub async fn get_clipboard_items_page(
+    cursor: Option<String>,
+    limit: u32,
+    app_handle: tauri::AppHandle,
+) -> Result<crate::clipboard::ClipboardPage, String> {
+    let app_data_dir = get_app_data_dir(&app_handle)?;
+    crate::clipboard::get_clipboard_items_page(cursor, limit, &app_data_dir)
+}
+
+#[tauri::command]
+pub async fn search_clipboard_items_page(
+    query: String,
+    cursor: Option<String>,
+    limit: u32,
+    app_handle: tauri::AppHandle,
+) -> Result<crate::clipboard::ClipboardPage, String> {
+    let app_data_dir = get_app_data_dir(&app_handle)?;
+    crate::clipboard::search_clipboard_items_page(&query, cursor, limit, &app_data_dir)
 }
 
 #[tauri::command]
@@ -6445,96 +6989,8 @@ pub async fn get_clipboard_image_data(image_path: String) -> Result<Vec<u8>, Str
 pub async fn copy_image_to_clipboard(image_path: String) -> Result<(), String> {
     #[cfg(target_os = "windows")]
     {
-        use std::fs;
-        use windows_sys::Win32::System::DataExchange::{
-            SetClipboardData, OpenClipboard, EmptyClipboard, CloseClipboard,
-        };
-        use windows_sys::Win32::System::Memory::{GlobalAlloc, GlobalLock, GlobalUnlock, GMEM_MOVEABLE};
-        use windows_sys::Win32::Foundation::HWND;
-        use windows_sys::Win32::Graphics::Gdi::BITMAPINFOHEADER;
-
-        // 读取图片文件
-        let image_data = fs::read(&image_path)
-            .map_err(|e| format!("Failed to read image: {}", e))?;
-
-        // 解码 PNG
-        let decoder = png::Decoder::new(&image_data[..]);
-        let mut reader = decoder.read_info()
-            .map_err(|e| format!("Failed to decode PNG: {}", e))?;
-        let mut buf = vec![0; reader.output_buffer_size()];
-        let info = reader.next_frame(&mut buf)
-            .map_err(|e| format!("Failed to read PNG frame: {}", e))?;
-
-        let width = info.width as i32;
-        let height = info.height as i32;
-        let bit_count = 32u16; // RGBA
-
-        unsafe {
-            if OpenClipboard(0 as HWND) == 0 {
-                return Err("Failed to open clipboard".to_string());
-            }
-
-            EmptyClipboard();
-
-            // 计算 DIB 大小
-            let header_size = std::mem::size_of::<BITMAPINFOHEADER>();
-            let row_size = ((width * bit_count as i32 + 31) / 32 * 4) as usize;
-            let image_size = row_size * height as usize;
-            let total_size = header_size + image_size;
-
-            // 分配全局内存
-            let h_mem = GlobalAlloc(GMEM_MOVEABLE, total_size);
-            if h_mem.is_null() {
-                CloseClipboard();
-                return Err("Failed to allocate memory".to_string());
-            }
-
-            let p_mem = GlobalLock(h_mem);
-            if p_mem.is_null() {
-                CloseClipboard();
-                return Err("Failed to lock memory".to_string());
-            }
-
-            // 填充 BITMAPINFOHEADER
-            let bmi = p_mem as *mut BITMAPINFOHEADER;
-            (*bmi).biSize = header_size as u32;
-            (*bmi).biWidth = width;
-            (*bmi).biHeight = height;
-            (*bmi).biPlanes = 1;
-            (*bmi).biBitCount = bit_count;
-            (*bmi).biCompression = 0; // BI_RGB
-            (*bmi).biSizeImage = 0;
-            (*bmi).biXPelsPerMeter = 0;
-            (*bmi).biYPelsPerMeter = 0;
-            (*bmi).biClrUsed = 0;
-            (*bmi).biClrImportant = 0;
-
-            // 填充图片数据（转换 RGBA 到 BGRA，并上下翻转）
-            let image_data_ptr = (p_mem as *mut u8).add(header_size);
-            for y in 0..height {
-                for x in 0..width {
-                    let src_offset = ((height - 1 - y) * width + x) as usize * 4;
-                    let dst_offset = (y * width + x) as usize * 4;
-                    if src_offset + 3 < buf.len() {
-                        *image_data_ptr.add(dst_offset) = buf[src_offset + 2]; // B
-                        *image_data_ptr.add(dst_offset + 1) = buf[src_offset + 1]; // G
-                        *image_data_ptr.add(dst_offset + 2) = buf[src_offset]; // R
-                        *image_data_ptr.add(dst_offset + 3) = buf[src_offset + 3]; // A
-                    }
-                }
-            }
-
-            GlobalUnlock(h_mem);
-
-            // 设置到剪切板 (CF_DIB = 8)
-            if SetClipboardData(8, h_mem as isize) == 0 {
-                CloseClipboard();
-                return Err("Failed to set clipboard data".to_string());
-            }
-
-            CloseClipboard();
-            Ok(())
-        }
+        // 写回和去重状态同步都在 monitor 模块里，避免刚写回的图片被再次入库
+        crate::clipboard::monitor::set_clipboard_image(&std::path::PathBuf::from(&image_path))
     }
 
     #[cfg(not(target_os = "windows"))]
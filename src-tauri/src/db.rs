@@ -214,6 +214,284 @@ fn run_migrations(conn: &Connection) -> Result<(), String> {
         .map_err(|e| format!("Failed to add ai_explanation column: {}", e))?;
     }
 
+    // Migration: Add raw_content column to clipboard_history if it doesn't exist
+    // Stores the exact original text when normalization changed the stored content
+    let raw_content_exists = conn
+        .prepare("SELECT raw_content FROM clipboard_history LIMIT 1")
+        .is_ok();
+
+    if !raw_content_exists {
+        conn.execute(
+            "ALTER TABLE clipboard_history ADD COLUMN raw_content TEXT",
+            [],
+        )
+        .map_err(|e| format!("Failed to add raw_content column: {}", e))?;
+    }
+
+    // Migration: Add source_app column to clipboard_history if it doesn't exist
+    // Records which application the content was copied from (nullable)
+    let source_app_exists = conn
+        .prepare("SELECT source_app FROM clipboard_history LIMIT 1")
+        .is_ok();
+
+    if !source_app_exists {
+        conn.execute(
+            "ALTER TABLE clipboard_history ADD COLUMN source_app TEXT",
+            [],
+        )
+        .map_err(|e| format!("Failed to add source_app column: {}", e))?;
+    }
+
+    // Migration: Add note column to clipboard_history if it doesn't exist
+    // Free-form annotation, e.g. the source filename for imported items
+    let note_exists = conn
+        .prepare("SELECT note FROM clipboard_history LIMIT 1")
+        .is_ok();
+
+    if !note_exists {
+        conn.execute("ALTER TABLE clipboard_history ADD COLUMN note TEXT", [])
+            .map_err(|e| format!("Failed to add note column: {}", e))?;
+    }
+
+    // Migration: Add dominant_color column to clipboard_history if it doesn't exist
+    // Cached "#rrggbb" dominant color for image items
+    let dominant_color_exists = conn
+        .prepare("SELECT dominant_color FROM clipboard_history LIMIT 1")
+        .is_ok();
+
+    if !dominant_color_exists {
+        conn.execute(
+            "ALTER TABLE clipboard_history ADD COLUMN dominant_color TEXT",
+            [],
+        )
+        .map_err(|e| format!("Failed to add dominant_color column: {}", e))?;
+    }
+
+    // Migration: Add table_rows/table_cols columns to clipboard_history if they don't exist
+    // Detected dimensions for tabular ("table") items
+    let table_cols_exist = conn
+        .prepare("SELECT table_rows, table_cols FROM clipboard_history LIMIT 1")
+        .is_ok();
+
+    if !table_cols_exist {
+        conn.execute(
+            "ALTER TABLE clipboard_history ADD COLUMN table_rows INTEGER",
+            [],
+        )
+        .map_err(|e| format!("Failed to add table_rows column: {}", e))?;
+        conn.execute(
+            "ALTER TABLE clipboard_history ADD COLUMN table_cols INTEGER",
+            [],
+        )
+        .map_err(|e| format!("Failed to add table_cols column: {}", e))?;
+    }
+
+    // Migration: Add group_id column to clipboard_history if it doesn't exist
+    // Items captured from the same multi-file copy share a group id
+    let group_id_exists = conn
+        .prepare("SELECT group_id FROM clipboard_history LIMIT 1")
+        .is_ok();
+
+    if !group_id_exists {
+        conn.execute(
+            "ALTER TABLE clipboard_history ADD COLUMN group_id TEXT",
+            [],
+        )
+        .map_err(|e| format!("Failed to add group_id column: {}", e))?;
+    }
+
+    // Migration: Add title column to clipboard_history if it doesn't exist
+    // One-line display label derived from content on insert/edit
+    let title_exists = conn
+        .prepare("SELECT title FROM clipboard_history LIMIT 1")
+        .is_ok();
+
+    if !title_exists {
+        conn.execute("ALTER TABLE clipboard_history ADD COLUMN title TEXT", [])
+            .map_err(|e| format!("Failed to add title column: {}", e))?;
+    }
+
+    // Migration: Add tags column to clipboard_history if it doesn't exist
+    // JSON array of tag strings, same storage scheme as word_records.tags
+    let clipboard_tags_exist = conn
+        .prepare("SELECT tags FROM clipboard_history LIMIT 1")
+        .is_ok();
+
+    if !clipboard_tags_exist {
+        conn.execute("ALTER TABLE clipboard_history ADD COLUMN tags TEXT", [])
+            .map_err(|e| format!("Failed to add tags column: {}", e))?;
+    }
+
+    // Migration: Add mime_type/image_bytes columns to clipboard_history if they don't exist
+    // Stored format and on-disk size for image items
+    let mime_type_exists = conn
+        .prepare("SELECT mime_type, image_bytes FROM clipboard_history LIMIT 1")
+        .is_ok();
+
+    if !mime_type_exists {
+        conn.execute(
+            "ALTER TABLE clipboard_history ADD COLUMN mime_type TEXT",
+            [],
+        )
+        .map_err(|e| format!("Failed to add mime_type column: {}", e))?;
+        conn.execute(
+            "ALTER TABLE clipboard_history ADD COLUMN image_bytes INTEGER",
+            [],
+        )
+        .map_err(|e| format!("Failed to add image_bytes column: {}", e))?;
+    }
+
+    // Migration: Add content_hash column to clipboard_history if it doesn't exist
+    // SHA-256 of the content, for privacy-preserving dedup lookups
+    let content_hash_exists = conn
+        .prepare("SELECT content_hash FROM clipboard_history LIMIT 1")
+        .is_ok();
+
+    if !content_hash_exists {
+        conn.execute(
+            "ALTER TABLE clipboard_history ADD COLUMN content_hash TEXT",
+            [],
+        )
+        .map_err(|e| format!("Failed to add content_hash column: {}", e))?;
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_clipboard_history_content_hash ON clipboard_history(content_hash)",
+            [],
+        )
+        .map_err(|e| format!("Failed to create content_hash index: {}", e))?;
+    }
+
+    // Migration: Normalized storage for Everything custom filters
+    // Replaces the single JSON blob under settings('everything_custom_filters')
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS custom_filters (
+            id TEXT PRIMARY KEY,
+            label TEXT NOT NULL,
+            extensions TEXT NOT NULL,
+            enabled INTEGER NOT NULL DEFAULT 1,
+            sort_order INTEGER NOT NULL DEFAULT 0,
+            use_count INTEGER NOT NULL DEFAULT 0
+        )",
+        [],
+    )
+    .map_err(|e| format!("Failed to create custom_filters table: {}", e))?;
+
+    // Migration: Curated snippets promoted out of the rolling clipboard history
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS snippets (
+            id TEXT PRIMARY KEY,
+            label TEXT NOT NULL,
+            content TEXT NOT NULL,
+            content_type TEXT NOT NULL,
+            created_at INTEGER NOT NULL
+        )",
+        [],
+    )
+    .map_err(|e| format!("Failed to create snippets table: {}", e))?;
+
+    // Migration: Add profile column to clipboard_history if it doesn't exist
+    // NULL means the default profile
+    let profile_exists = conn
+        .prepare("SELECT profile FROM clipboard_history LIMIT 1")
+        .is_ok();
+
+    if !profile_exists {
+        conn.execute("ALTER TABLE clipboard_history ADD COLUMN profile TEXT", [])
+            .map_err(|e| format!("Failed to add profile column: {}", e))?;
+    }
+
+    // Migration: Add thumbnail_path column for downscaled image previews (nullable)
+    let thumbnail_exists = conn
+        .prepare("SELECT thumbnail_path FROM clipboard_history LIMIT 1")
+        .is_ok();
+
+    if !thumbnail_exists {
+        conn.execute(
+            "ALTER TABLE clipboard_history ADD COLUMN thumbnail_path TEXT",
+            [],
+        )
+        .map_err(|e| format!("Failed to add thumbnail_path column: {}", e))?;
+    }
+
+    // Migration: Add truncated flag for text items clipped at the size limit
+    let truncated_exists = conn
+        .prepare("SELECT truncated FROM clipboard_history LIMIT 1")
+        .is_ok();
+
+    if !truncated_exists {
+        conn.execute(
+            "ALTER TABLE clipboard_history ADD COLUMN truncated INTEGER NOT NULL DEFAULT 0",
+            [],
+        )
+        .map_err(|e| format!("Failed to add truncated column: {}", e))?;
+    }
+
+    // Migration: Add kind column for detected text subtypes (url, email, ...)
+    let kind_exists = conn
+        .prepare("SELECT kind FROM clipboard_history LIMIT 1")
+        .is_ok();
+
+    if !kind_exists {
+        conn.execute("ALTER TABLE clipboard_history ADD COLUMN kind TEXT", [])
+            .map_err(|e| format!("Failed to add kind column: {}", e))?;
+    }
+
+    // Migration: Add rich text columns (HTML fragment or RTF captured alongside plain text)
+    let rich_exists = conn
+        .prepare("SELECT rich_content FROM clipboard_history LIMIT 1")
+        .is_ok();
+
+    if !rich_exists {
+        conn.execute(
+            "ALTER TABLE clipboard_history ADD COLUMN rich_content TEXT",
+            [],
+        )
+        .map_err(|e| format!("Failed to add rich_content column: {}", e))?;
+        conn.execute(
+            "ALTER TABLE clipboard_history ADD COLUMN rich_format TEXT",
+            [],
+        )
+        .map_err(|e| format!("Failed to add rich_format column: {}", e))?;
+    }
+
+    // Migration: FTS5 full-text index over clipboard content, kept in sync
+    // with clipboard_history via triggers (external content table)
+    let fts_existed = conn
+        .query_row(
+            "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = 'clipboard_fts'",
+            [],
+            |row| row.get::<_, i64>(0),
+        )
+        .map(|n| n > 0)
+        .unwrap_or(false);
+
+    conn.execute_batch(
+        "CREATE VIRTUAL TABLE IF NOT EXISTS clipboard_fts USING fts5(
+            content,
+            content='clipboard_history',
+            content_rowid='rowid'
+        );
+        CREATE TRIGGER IF NOT EXISTS clipboard_history_fts_ai AFTER INSERT ON clipboard_history BEGIN
+            INSERT INTO clipboard_fts(rowid, content) VALUES (new.rowid, new.content);
+        END;
+        CREATE TRIGGER IF NOT EXISTS clipboard_history_fts_ad AFTER DELETE ON clipboard_history BEGIN
+            INSERT INTO clipboard_fts(clipboard_fts, rowid, content) VALUES ('delete', old.rowid, old.content);
+        END;
+        CREATE TRIGGER IF NOT EXISTS clipboard_history_fts_au AFTER UPDATE OF content ON clipboard_history BEGIN
+            INSERT INTO clipboard_fts(clipboard_fts, rowid, content) VALUES ('delete', old.rowid, old.content);
+            INSERT INTO clipboard_fts(rowid, content) VALUES (new.rowid, new.content);
+        END;",
+    )
+    .map_err(|e| format!("Failed to create clipboard FTS index: {}", e))?;
+
+    if !fts_existed {
+        // Backfill the index from existing rows on first creation
+        conn.execute(
+            "INSERT INTO clipboard_fts(clipboard_fts) VALUES('rebuild')",
+            [],
+        )
+        .map_err(|e| format!("Failed to backfill clipboard FTS index: {}", e))?;
+    }
+
     // Migration: Remove source_lang and target_lang columns if they exist
     // SQLite doesn't support DROP COLUMN, so we need to recreate the table
     let old_columns_exist = conn
@@ -269,3 +547,68 @@ fn run_migrations(conn: &Connection) -> Result<(), String> {
     Ok(())
 }
 
+
+/// Read-only schema diagnostics for bug reports and migration checks.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct DbInfo {
+    pub user_version: i64,
+    pub journal_mode: String,
+    pub wal_enabled: bool,
+    pub tables: Vec<TableInfo>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct TableInfo {
+    pub name: String,
+    pub rows: u64,
+}
+
+/// Collect `user_version`, journal mode and per-table row counts.
+pub fn get_db_info(app_data_dir: &Path) -> Result<DbInfo, String> {
+    let conn = get_connection(app_data_dir)?;
+
+    let user_version: i64 = conn
+        .query_row("PRAGMA user_version", [], |row| row.get(0))
+        .map_err(|e| format!("Failed to read user_version: {}", e))?;
+
+    let journal_mode: String = conn
+        .query_row("PRAGMA journal_mode", [], |row| row.get(0))
+        .map_err(|e| format!("Failed to read journal_mode: {}", e))?;
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT name FROM sqlite_master
+             WHERE type = 'table' AND name NOT LIKE 'sqlite_%'
+             ORDER BY name",
+        )
+        .map_err(|e| format!("Failed to list tables: {}", e))?;
+
+    let names: Vec<String> = stmt
+        .query_map([], |row| row.get(0))
+        .map_err(|e| format!("Failed to query tables: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to read table names: {}", e))?;
+    drop(stmt);
+
+    let mut tables = Vec::new();
+    for name in names {
+        // Table names come from sqlite_master, not user input.
+        let rows: i64 = conn
+            .query_row(&format!("SELECT COUNT(*) FROM \"{}\"", name), [], |row| {
+                row.get(0)
+            })
+            .map_err(|e| format!("Failed to count rows in {}: {}", name, e))?;
+        tables.push(TableInfo {
+            name,
+            rows: rows as u64,
+        });
+    }
+
+    let wal_enabled = journal_mode.eq_ignore_ascii_case("wal");
+    Ok(DbInfo {
+        user_version,
+        journal_mode,
+        wal_enabled,
+        tables,
+    })
+}
@@ -27,5 +27,42 @@ impl From<AppError> for String {
     }
 }
 
+/// 剪切板与过滤器模块的类型化错误，让调用方能区分
+/// “条目不存在”和数据库/IO 故障。逐步替换两个模块里的
+/// Result<_, String>；现有 String 签名经 From 转换保持兼容
+#[derive(Error, Debug)]
+pub enum ClipboardError {
+    #[error("Clipboard item not found")]
+    NotFound,
+
+    #[error("Database error: {0}")]
+    Database(#[from] rusqlite::Error),
+
+    #[error("Serialization error: {0}")]
+    Serialization(#[from] serde_json::Error),
+
+    #[error("Clipboard access error: {0}")]
+    ClipboardAccess(String),
+
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    /// 尚未迁移到类型化错误的内部调用透传的消息
+    #[error("{0}")]
+    Other(String),
+}
+
+impl From<ClipboardError> for String {
+    fn from(error: ClipboardError) -> Self {
+        error.to_string()
+    }
+}
+
+impl From<String> for ClipboardError {
+    fn from(message: String) -> Self {
+        ClipboardError::Other(message)
+    }
+}
+
 
 
@@ -8,12 +8,167 @@ pub struct CustomFilter {
     pub id: String,
     pub label: String,
     pub extensions: Vec<String>,
+    /// 是否启用
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    /// 列表中的显示顺序
+    #[serde(default)]
+    pub sort_order: u32,
+    /// 使用次数统计
+    #[serde(default)]
+    pub use_count: u64,
 }
 
-/// 加载自定义过滤器列表
-pub fn load_custom_filters(app_data_dir: &Path) -> Result<Vec<CustomFilter>, String> {
-    let conn = db::get_connection(app_data_dir)?;
-    
+fn default_enabled() -> bool {
+    true
+}
+
+impl CustomFilter {
+    /// 生成 Everything 搜索表达式：`<搜索词> ext:rs;toml;md`。
+    /// 扩展名列表为空时原样返回搜索词；含空格或运算符的词加引号
+    pub fn to_everything_query(&self, base_terms: &str) -> String {
+        let terms = base_terms
+            .split_whitespace()
+            .map(escape_everything_term)
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        if self.extensions.is_empty() {
+            return terms;
+        }
+
+        let ext_expr = format!("ext:{}", self.extensions.join(";"));
+        if terms.is_empty() {
+            ext_expr
+        } else {
+            format!("{} {}", terms, ext_expr)
+        }
+    }
+
+    /// Everything 不可用时的本地兜底：按扩展名（大小写不敏感）匹配路径。
+    /// 扩展名列表为空视为不限制
+    pub fn matches_path(&self, path: &Path) -> bool {
+        if self.extensions.is_empty() {
+            return true;
+        }
+        match path.extension().and_then(|e| e.to_str()) {
+            Some(ext) => {
+                let ext = ext.to_lowercase();
+                self.extensions.iter().any(|e| e.to_lowercase() == ext)
+            }
+            None => false,
+        }
+    }
+}
+
+/// 含空格或 Everything 运算符（| ! < > ( )）的词整体加引号，
+/// 避免被当成语法解析；词内的引号去掉（Everything 无转义语法）
+fn escape_everything_term(term: &str) -> String {
+    let needs_quotes = term
+        .chars()
+        .any(|c| c.is_whitespace() || matches!(c, '|' | '!' | '<' | '>' | '(' | ')'));
+    if needs_quotes {
+        format!("\"{}\"", term.replace('"', ""))
+    } else {
+        term.to_string()
+    }
+}
+
+/// 宽松的中间结构：旧版本的存量 JSON 缺字段也能反序列化，
+/// CustomFilter 之后再加字段时不会让旧安装直接报错
+#[derive(Deserialize, Debug, Default)]
+struct CustomFilterCompat {
+    #[serde(default)]
+    id: Option<String>,
+    #[serde(default)]
+    label: Option<String>,
+    #[serde(default)]
+    extensions: Vec<String>,
+    #[serde(default)]
+    enabled: Option<bool>,
+    #[serde(default)]
+    sort_order: Option<u32>,
+    #[serde(default)]
+    use_count: Option<u64>,
+}
+
+/// 宽松解析过滤器 JSON，缺失字段补默认值
+fn parse_filters_lenient(json: &str) -> Result<Vec<CustomFilter>, String> {
+    let raw: Vec<CustomFilterCompat> = serde_json::from_str(json)
+        .map_err(|e| format!("Failed to parse custom filters from database: {}", e))?;
+
+    Ok(raw
+        .into_iter()
+        .enumerate()
+        .map(|(index, f)| CustomFilter {
+            id: f.id.unwrap_or_else(|| format!("filter-{}", index)),
+            label: f.label.unwrap_or_default(),
+            extensions: f.extensions,
+            enabled: f.enabled.unwrap_or(true),
+            sort_order: f.sort_order.unwrap_or(index as u32),
+            use_count: f.use_count.unwrap_or(0),
+        })
+        .collect())
+}
+
+/// 规范化单个过滤器：扩展名去空白、去前导点、转小写、去空去重。
+/// label 为空或清洗后一个扩展名都不剩时返回 Err
+fn normalize_filter(filter: &CustomFilter) -> Result<CustomFilter, String> {
+    let label = filter.label.trim().to_string();
+    if label.is_empty() {
+        return Err(format!("Filter '{}' has a blank label", filter.id));
+    }
+
+    let mut seen = std::collections::HashSet::new();
+    let mut extensions = Vec::new();
+    for ext in &filter.extensions {
+        let ext = ext.trim().trim_start_matches('.').to_lowercase();
+        if ext.is_empty() {
+            continue;
+        }
+        if seen.insert(ext.clone()) {
+            extensions.push(ext);
+        }
+    }
+    if extensions.is_empty() {
+        return Err(format!("Filter '{}' has no valid extensions", label));
+    }
+
+    Ok(CustomFilter {
+        id: filter.id.trim().to_string(),
+        label,
+        extensions,
+        enabled: filter.enabled,
+        sort_order: filter.sort_order,
+        use_count: filter.use_count,
+    })
+}
+
+/// 整表规范化：逐个清洗，id 重复时整体拒绝
+fn normalize_filters(filters: &[CustomFilter]) -> Result<Vec<CustomFilter>, String> {
+    let mut seen_ids = std::collections::HashSet::new();
+    let mut normalized = Vec::with_capacity(filters.len());
+    for filter in filters {
+        let filter = normalize_filter(filter)?;
+        if !seen_ids.insert(filter.id.clone()) {
+            return Err(format!("Duplicate filter id '{}'", filter.id));
+        }
+        normalized.push(filter);
+    }
+    Ok(normalized)
+}
+
+/// 一次性迁移：custom_filters 表为空且还存在旧 JSON blob 时，
+/// 把 blob 内容逐行写入表并删除 blob，返回迁移的条数
+fn maybe_migrate_from_blob(conn: &rusqlite::Connection) -> Result<u32, String> {
+    let count: i64 = conn
+        .query_row("SELECT COUNT(*) FROM custom_filters", [], |row| row.get(0))
+        .map_err(|e| format!("Failed to count custom filters: {}", e))?;
+
+    if count > 0 {
+        return Ok(0);
+    }
+
     let value: Option<String> = conn
         .query_row(
             "SELECT value FROM settings WHERE key = 'everything_custom_filters' LIMIT 1",
@@ -23,28 +178,303 @@ pub fn load_custom_filters(app_data_dir: &Path) -> Result<Vec<CustomFilter>, Str
         .optional()
         .map_err(|e| format!("Failed to load custom filters from database: {}", e))?;
 
-    if let Some(json) = value {
-        serde_json::from_str(&json)
-            .map_err(|e| format!("Failed to parse custom filters from database: {}", e))
-    } else {
-        Ok(Vec::new())
+    let json = match value {
+        Some(json) => json,
+        None => return Ok(0),
+    };
+
+    let filters = parse_filters_lenient(&json)?;
+    for filter in &filters {
+        insert_filter(conn, filter)?;
     }
+
+    conn.execute(
+        "DELETE FROM settings WHERE key = 'everything_custom_filters'",
+        [],
+    )
+    .map_err(|e| format!("Failed to remove legacy filter blob: {}", e))?;
+
+    Ok(filters.len() as u32)
 }
 
-/// 保存自定义过滤器列表
-pub fn save_custom_filters(app_data_dir: &Path, filters: &[CustomFilter]) -> Result<(), String> {
+fn insert_filter(conn: &rusqlite::Connection, filter: &CustomFilter) -> Result<(), String> {
+    let extensions_json = serde_json::to_string(&filter.extensions)
+        .map_err(|e| format!("Failed to serialize filter extensions: {}", e))?;
+
+    conn.execute(
+        "INSERT INTO custom_filters (id, label, extensions, enabled, sort_order, use_count)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+         ON CONFLICT(id) DO UPDATE SET
+             label = excluded.label,
+             extensions = excluded.extensions,
+             enabled = excluded.enabled,
+             sort_order = excluded.sort_order,
+             use_count = excluded.use_count",
+        params![
+            filter.id,
+            filter.label,
+            extensions_json,
+            if filter.enabled { 1 } else { 0 },
+            filter.sort_order,
+            filter.use_count as i64
+        ],
+    )
+    .map_err(|e| format!("Failed to save custom filter: {}", e))?;
+
+    Ok(())
+}
+
+/// 加载自定义过滤器列表（首次访问时自动从旧 JSON blob 迁移）
+pub fn load_custom_filters(app_data_dir: &Path) -> Result<Vec<CustomFilter>, String> {
     let conn = db::get_connection(app_data_dir)?;
-    
-    let filters_json = serde_json::to_string(filters)
-        .map_err(|e| format!("Failed to serialize custom filters: {}", e))?;
+    maybe_migrate_from_blob(&conn)?;
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, label, extensions, enabled, sort_order, use_count
+             FROM custom_filters ORDER BY sort_order, id",
+        )
+        .map_err(|e| format!("Failed to prepare filter query: {}", e))?;
 
+    let filters = stmt
+        .query_map([], |row| {
+            let extensions_json: String = row.get(2)?;
+            Ok(CustomFilter {
+                id: row.get(0)?,
+                label: row.get(1)?,
+                extensions: serde_json::from_str(&extensions_json).unwrap_or_default(),
+                enabled: row.get::<_, i64>(3)? != 0,
+                sort_order: row.get::<_, i64>(4)? as u32,
+                use_count: row.get::<_, i64>(5)? as u64,
+            })
+        })
+        .map_err(|e| format!("Failed to query custom filters: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to read custom filters: {}", e))?;
+
+    Ok(filters)
+}
+
+/// 将旧 JSON blob 迁移到 custom_filters 表，返回迁移的条数
+pub fn migrate_filters(app_data_dir: &Path) -> Result<u32, String> {
+    let conn = db::get_connection(app_data_dir)?;
+    maybe_migrate_from_blob(&conn)
+}
+
+/// 读取当前过滤器版本号（每次整表保存递增，用于乐观并发控制）
+pub fn get_filters_version(app_data_dir: &Path) -> Result<u64, String> {
+    let conn = db::get_connection(app_data_dir)?;
+    read_version(&conn)
+}
+
+fn read_version(conn: &rusqlite::Connection) -> Result<u64, String> {
+    let value: Option<String> = conn
+        .query_row(
+            "SELECT value FROM settings WHERE key = 'custom_filters_version' LIMIT 1",
+            [],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| format!("Failed to load filters version: {}", e))?;
+
+    Ok(value.and_then(|v| v.parse().ok()).unwrap_or(0))
+}
+
+fn write_version(conn: &rusqlite::Connection, version: u64) -> Result<(), String> {
     conn.execute(
-        "INSERT INTO settings (key, value) VALUES ('everything_custom_filters', ?1)
+        "INSERT INTO settings (key, value) VALUES ('custom_filters_version', ?1)
          ON CONFLICT(key) DO UPDATE SET value = excluded.value",
-        params![filters_json],
+        params![version.to_string()],
     )
-    .map_err(|e| format!("Failed to save custom filters to database: {}", e))?;
+    .map_err(|e| format!("Failed to save filters version: {}", e))?;
+    Ok(())
+}
+
+/// 整表重写过滤器（一个事务内完成），并递增版本号
+fn rewrite_filters(
+    tx: &rusqlite::Transaction,
+    filters: &[CustomFilter],
+) -> Result<u64, String> {
+    tx.execute("DELETE FROM custom_filters", [])
+        .map_err(|e| format!("Failed to clear custom filters: {}", e))?;
+
+    for filter in filters {
+        insert_filter(tx, filter)?;
+    }
+
+    let new_version = read_version(tx)? + 1;
+    write_version(tx, new_version)?;
+    Ok(new_version)
+}
+
+/// 保存自定义过滤器列表（整表重写，一个事务内完成）。
+/// 保存前先规范化，非法过滤器或重复 id 直接拒绝；
+/// 旧的未规范化存量数据由 load_custom_filters 原样容忍
+pub fn save_custom_filters(app_data_dir: &Path, filters: &[CustomFilter]) -> Result<(), String> {
+    let filters = normalize_filters(filters)?;
+    let mut conn = db::get_connection(app_data_dir)?;
+    let tx = conn
+        .transaction()
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    rewrite_filters(&tx, &filters)?;
+
+    tx.commit()
+        .map_err(|e| format!("Failed to commit custom filters: {}", e))?;
 
     Ok(())
 }
 
+/// 导出全部过滤器为可分享的 JSON 文件，返回导出的条数
+pub fn export_custom_filters(app_data_dir: &Path, out_path: &Path) -> Result<u32, String> {
+    let filters = load_custom_filters(app_data_dir)?;
+    let json = serde_json::to_string_pretty(&filters)
+        .map_err(|e| format!("Failed to serialize custom filters: {}", e))?;
+
+    // 先写临时文件再改名，避免导出文件出现半截内容
+    let tmp_path = out_path.with_extension("tmp");
+    std::fs::write(&tmp_path, &json)
+        .map_err(|e| format!("Failed to write filter export: {}", e))?;
+    std::fs::rename(&tmp_path, out_path)
+        .map_err(|e| format!("Failed to replace filter export: {}", e))?;
+
+    Ok(filters.len() as u32)
+}
+
+/// 从 JSON 文件导入过滤器，经过与保存相同的规范化校验。
+/// replace 为 true 时整表替换；为 false 时按 id 合并，
+/// 与现有 id 冲突的导入项重新生成 id 而不是覆盖。返回合并后的完整列表
+pub fn import_custom_filters(
+    app_data_dir: &Path,
+    in_path: &Path,
+    replace: bool,
+) -> Result<Vec<CustomFilter>, String> {
+    let json = std::fs::read_to_string(in_path)
+        .map_err(|e| format!("Failed to read filter file: {}", e))?;
+    // 宽松解析容忍旧版导出缺字段，规范化负责最终校验
+    let imported = parse_filters_lenient(&json)?;
+    let imported = normalize_filters(&imported)?;
+
+    let merged = if replace {
+        imported
+    } else {
+        let mut merged = load_custom_filters(app_data_dir)?;
+        let mut ids: std::collections::HashSet<String> =
+            merged.iter().map(|f| f.id.clone()).collect();
+        for mut filter in imported {
+            if ids.contains(&filter.id) {
+                filter.id = deconflict_filter_id(&filter.id, &ids);
+            }
+            ids.insert(filter.id.clone());
+            merged.push(filter);
+        }
+        merged
+    };
+
+    save_custom_filters(app_data_dir, &merged)?;
+    Ok(merged)
+}
+
+/// 给冲突的 id 加数字后缀直到不再重复
+fn deconflict_filter_id(id: &str, existing: &std::collections::HashSet<String>) -> String {
+    let mut suffix = 2;
+    loop {
+        let candidate = format!("{}-{}", id, suffix);
+        if !existing.contains(&candidate) {
+            return candidate;
+        }
+        suffix += 1;
+    }
+}
+
+/// 乐观并发的整表替换：版本号与加载时不一致说明有并发修改，
+/// 返回冲突错误让调用方重新加载，避免丢更新；成功时返回新版本号
+pub fn replace_filters(
+    app_data_dir: &Path,
+    expected_version: u64,
+    filters: &[CustomFilter],
+) -> Result<u64, String> {
+    let filters = normalize_filters(filters)?;
+    let mut conn = db::get_connection(app_data_dir)?;
+    let tx = conn
+        .transaction()
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    let current = read_version(&tx)?;
+    if current != expected_version {
+        return Err(format!(
+            "Filter set was modified concurrently (expected version {}, found {})",
+            expected_version, current
+        ));
+    }
+
+    let new_version = rewrite_filters(&tx, &filters)?;
+
+    tx.commit()
+        .map_err(|e| format!("Failed to commit custom filters: {}", e))?;
+
+    Ok(new_version)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filter(id: &str, label: &str, extensions: &[&str]) -> CustomFilter {
+        CustomFilter {
+            id: id.to_string(),
+            label: label.to_string(),
+            extensions: extensions.iter().map(|e| e.to_string()).collect(),
+            enabled: true,
+            sort_order: 0,
+            use_count: 0,
+        }
+    }
+
+    #[test]
+    fn test_to_everything_query_joins_extensions() {
+        let single = filter("f1", "Rust", &["rs"]);
+        assert_eq!(single.to_everything_query("main"), "main ext:rs");
+
+        let many = filter("f2", "Docs", &["rs", "toml", "md"]);
+        assert_eq!(many.to_everything_query("read me"), "read me ext:rs;toml;md");
+        assert_eq!(many.to_everything_query(""), "ext:rs;toml;md");
+
+        let none = filter("f3", "All", &[]);
+        assert_eq!(none.to_everything_query("query"), "query");
+    }
+
+    #[test]
+    fn test_to_everything_query_escapes_operators() {
+        let f = filter("f1", "Rust", &["rs"]);
+        assert_eq!(f.to_everything_query("a|b"), "\"a|b\" ext:rs");
+        assert_eq!(f.to_everything_query("foo(1)"), "\"foo(1)\" ext:rs");
+    }
+
+    #[test]
+    fn test_matches_path_checks_extension() {
+        let f = filter("f1", "Rust", &["rs", "toml"]);
+        assert!(f.matches_path(Path::new("src/main.rs")));
+        assert!(f.matches_path(Path::new("Cargo.TOML")));
+        assert!(!f.matches_path(Path::new("readme.md")));
+        assert!(!f.matches_path(Path::new("no_extension")));
+    }
+
+    #[test]
+    fn test_normalize_filter_cleans_extensions() {
+        let normalized = normalize_filter(&filter("f1", " Docs ", &[".RS", "rs", " .toml ", ""])).unwrap();
+        assert_eq!(normalized.label, "Docs");
+        assert_eq!(normalized.extensions, vec!["rs", "toml"]);
+    }
+
+    #[test]
+    fn test_normalize_filters_rejects_invalid() {
+        assert!(normalize_filter(&filter("f1", "  ", &["rs"])).is_err());
+        assert!(normalize_filter(&filter("f1", "Docs", &[" . ", ""])).is_err());
+        assert!(normalize_filters(&[
+            filter("f1", "Docs", &["rs"]),
+            filter("f1", "Other", &["md"]),
+        ])
+        .is_err());
+    }
+}
@@ -658,8 +658,16 @@ fn main() {
                 }
             });
 
-            // Start clipboard monitor on Windows
-            #[cfg(target_os = "windows")]
+            // 记录本次启动时间，作为"本会话"列表的默认起点
+            clipboard::init_session();
+
+            // 收藏自动备份（未配置备份路径时线程空转跳过）
+            if let Err(e) = clipboard::start_favorite_backup(app_data_dir.clone()) {
+                eprintln!("[Main] Failed to start favorite backup: {}", e);
+            }
+
+            // Start clipboard monitor (event-driven on Windows, polling elsewhere)
+            #[cfg(any(target_os = "windows", target_os = "macos", target_os = "linux"))]
             {
                 let app_data_dir_clipboard = app_data_dir.clone();
                 if let Err(e) = clipboard::monitor::start_clipboard_monitor(app_data_dir_clipboard) {
@@ -669,6 +677,24 @@ fn main() {
                 }
             }
 
+            // Linux 下按设置监控 PRIMARY 选区
+            #[cfg(target_os = "linux")]
+            {
+                let primary_enabled = settings::load_settings(&app_data_dir)
+                    .map(|s| s.clipboard_capture_primary)
+                    .unwrap_or(false);
+                if primary_enabled {
+                    let app_data_dir_primary = app_data_dir.clone();
+                    if let Err(e) =
+                        clipboard::primary_monitor::start_primary_monitor(app_data_dir_primary)
+                    {
+                        eprintln!("[Main] Failed to start PRIMARY selection monitor: {}", e);
+                    } else {
+                        eprintln!("[Main] PRIMARY selection monitor started");
+                    }
+                }
+            }
+
             // 预热拾色器资源（后台线程，避免阻塞启动）
             // commands::color_picker::warmup_color_picker();  // 暂时屏蔽，待优化
 
@@ -779,6 +805,11 @@ fn main() {
             save_settings,
             get_everything_custom_filters,
             save_everything_custom_filters,
+            migrate_everything_custom_filters,
+            get_everything_filters_version,
+            replace_everything_custom_filters,
+            export_everything_custom_filters,
+            import_everything_custom_filters,
             is_startup_enabled,
             set_startup_enabled,
             get_hotkey_config,
@@ -802,8 +833,63 @@ fn main() {
             update_clipboard_item,
             toggle_favorite_clipboard_item,
             delete_clipboard_item,
+            delete_clipboard_items,
+            set_favorite_clipboard_items,
             clear_clipboard_history,
             search_clipboard_items,
+            delete_clipboard_items_by_source,
+            import_clipboard_text_files,
+            compute_clipboard_dominant_color,
+            clipboard_content_matches_blocklist,
+            add_clipboard_content_to_blocklist,
+            collapse_clipboard_cross_type_duplicates,
+            export_clipboard_filtered,
+            save_clipboard_item_as_snippet,
+            get_clipboard_snippets,
+            delete_clipboard_snippet,
+            get_clipboard_items_offset,
+            get_clipboard_storage_usage,
+            enforce_clipboard_total_budget,
+            export_clipboard_history,
+            import_clipboard_history,
+            enable_clipboard_encryption,
+            disable_clipboard_encryption,
+            unlock_clipboard_encryption,
+            lock_clipboard_encryption,
+            get_clipboard_encryption_status,
+            get_db_info,
+            get_clipboard_monitor_dedup_state,
+            reset_clipboard_monitor_dedup_state,
+            snapshot_clipboard_ids,
+            diff_clipboard_since,
+            get_clipboard_monitor_status,
+            pause_clipboard_monitor,
+            resume_clipboard_monitor,
+            get_clipboard_monitor_paused,
+            set_clipboard_poll_interval,
+            copy_latest_clipboard_of_type,
+            extract_clipboard_item_links,
+            rebuild_clipboard_fts_index,
+            check_clipboard_fts_consistency,
+            get_clipboard_session_items,
+            reset_clipboard_session,
+            capture_screen_region,
+            move_clipboard_items_to_profile,
+            add_clipboard_item_force,
+            add_clipboard_item_at,
+            get_clipboard_growth_stats,
+            find_clipboard_item_by_hash,
+            toggle_favorite_and_list,
+            audit_clipboard_content_types,
+            copy_clipboard_items_combined,
+            reencode_clipboard_images,
+            set_clipboard_item_tags,
+            list_clipboard_tags,
+            add_clipboard_file_paths,
+            verify_clipboard_no_duplicates,
+            resolve_clipboard_duplicates,
+            get_clipboard_items_page,
+            search_clipboard_items_page,
             show_clipboard_window,
             get_clipboard_image_data,
             copy_image_to_clipboard,
@@ -30,6 +30,69 @@ pub struct Settings {
     pub ignored_update_version: Option<String>,
     #[serde(default = "default_clipboard_max_items")]
     pub clipboard_max_items: u32,
+    /// 非收藏项的最长保留秒数，超期自动清理，0 表示不按时间清理（默认 30 天）
+    #[serde(default = "default_clipboard_max_age_secs")]
+    pub clipboard_max_age_secs: u64,
+    /// 文本内容的最大入库字节数，超出部分截断，0 表示不限制（默认 1 MiB）
+    #[serde(default = "default_clipboard_max_text_bytes")]
+    pub clipboard_max_text_bytes: u64,
+    #[serde(default)]
+    pub clipboard_normalize_text: bool,
+    /// 文本入库和搜索词统一做 NFC 归一化（不同来源的等价字符合并去重）
+    #[serde(default)]
+    pub clipboard_unicode_nfc: bool,
+    #[serde(default)]
+    pub clipboard_favorite_on_edit: bool,
+    /// 文件复制的入库方式："single" 合成一条，"per_file" 每个文件一条
+    #[serde(default = "default_file_capture_mode")]
+    pub clipboard_file_capture_mode: String,
+    /// Linux 下是否同时监控 PRIMARY 选区（中键粘贴）
+    #[serde(default)]
+    pub clipboard_capture_primary: bool,
+    /// 列表预览的最大字符数
+    #[serde(default = "default_preview_max_chars")]
+    pub clipboard_preview_max_chars: u32,
+    /// 预览是否把换行折叠成空格（多行内容单行显示）
+    #[serde(default = "default_preview_collapse_newlines")]
+    pub clipboard_preview_collapse_newlines: bool,
+    /// 来源应用记录完整可执行文件路径而不是基名（跨版本过滤更稳定）
+    #[serde(default)]
+    pub clipboard_source_full_path: bool,
+    /// 捕获时把来源应用写进备注（"from <app>"），来源解析失败则跳过
+    #[serde(default)]
+    pub clipboard_note_source_app: bool,
+    /// 收藏自动备份的目标文件路径，未设置表示关闭
+    #[serde(default)]
+    pub clipboard_favorite_backup_path: Option<String>,
+    /// 去重指纹用的正则列表：匹配部分（时间戳、流水号等）先抹掉再比较
+    /// 原始内容原样入库，只有去重键用指纹
+    #[serde(default)]
+    pub clipboard_fingerprint_patterns: Vec<String>,
+    /// 一次复制携带多种格式时的主格式优先级（靠前优先）
+    #[serde(default = "default_format_priority")]
+    pub clipboard_format_priority: Vec<String>,
+    /// 按窗口类名排除捕获（如密码对话框），大小写不敏感
+    #[serde(default)]
+    pub clipboard_excluded_window_classes: Vec<String>,
+    /// 前台窗口全屏（游戏、放映）时暂停剪切板捕获
+    #[serde(default)]
+    pub clipboard_suppress_fullscreen: bool,
+    /// 磁盘可用空间低于该字节数时跳过图片保存，0 表示不检查
+    #[serde(default)]
+    pub clipboard_min_free_bytes: u64,
+    /// 剪切板图片占用磁盘的上限字节数，0 表示不限制
+    #[serde(default)]
+    pub clipboard_max_image_bytes: u64,
+    /// 数据库与图片合计的磁盘预算字节数，0 表示不限制（默认 2 GB）
+    #[serde(default = "default_total_budget_bytes")]
+    pub clipboard_total_budget_bytes: u64,
+    /// 各类型独立的保留上限，未设置表示该类型不限制
+    #[serde(default)]
+    pub clipboard_cap_text: Option<u32>,
+    #[serde(default)]
+    pub clipboard_cap_image: Option<u32>,
+    #[serde(default)]
+    pub clipboard_cap_file: Option<u32>,
     #[serde(default = "default_translation_tab_order")]
     pub translation_tab_order: Vec<String>,
     #[serde(default = "default_search_engines")]
@@ -40,6 +103,39 @@ fn default_clipboard_max_items() -> u32 {
     100
 }
 
+fn default_clipboard_max_age_secs() -> u64 {
+    30 * 24 * 60 * 60
+}
+
+fn default_clipboard_max_text_bytes() -> u64 {
+    1024 * 1024
+}
+
+fn default_file_capture_mode() -> String {
+    "single".to_string()
+}
+
+fn default_format_priority() -> Vec<String> {
+    vec![
+        "image".to_string(),
+        "html".to_string(),
+        "rtf".to_string(),
+        "text".to_string(),
+    ]
+}
+
+fn default_total_budget_bytes() -> u64 {
+    2 * 1024 * 1024 * 1024
+}
+
+fn default_preview_max_chars() -> u32 {
+    120
+}
+
+fn default_preview_collapse_newlines() -> bool {
+    true
+}
+
 fn default_result_style() -> String {
     "skeuomorphic".to_string()
 }
@@ -71,6 +167,28 @@ impl Default for Settings {
             last_update_check_time: None,
             ignored_update_version: None,
             clipboard_max_items: default_clipboard_max_items(),
+            clipboard_max_age_secs: default_clipboard_max_age_secs(),
+            clipboard_max_text_bytes: default_clipboard_max_text_bytes(),
+            clipboard_normalize_text: false,
+            clipboard_unicode_nfc: false,
+            clipboard_favorite_on_edit: false,
+            clipboard_file_capture_mode: default_file_capture_mode(),
+            clipboard_capture_primary: false,
+            clipboard_preview_max_chars: default_preview_max_chars(),
+            clipboard_preview_collapse_newlines: default_preview_collapse_newlines(),
+            clipboard_source_full_path: false,
+            clipboard_note_source_app: false,
+            clipboard_favorite_backup_path: None,
+            clipboard_fingerprint_patterns: Vec::new(),
+            clipboard_format_priority: default_format_priority(),
+            clipboard_excluded_window_classes: Vec::new(),
+            clipboard_suppress_fullscreen: false,
+            clipboard_min_free_bytes: 0,
+            clipboard_max_image_bytes: 0,
+            clipboard_total_budget_bytes: default_total_budget_bytes(),
+            clipboard_cap_text: None,
+            clipboard_cap_image: None,
+            clipboard_cap_file: None,
             translation_tab_order: default_translation_tab_order(),
             search_engines: default_search_engines(),
         }